@@ -0,0 +1,223 @@
+use std::f64;
+
+use rand::{Rng};
+
+use driver::{DriverConfiguration};
+use mutation::{ConfigurationError};
+
+// Decides if Network::optimize() continues from a mutated network that is not better than the current one
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AcceptancePolicy {
+    // Only improvements are accepted
+    HillClimbing,
+    // Worse networks are accepted with probability exp(-relative_worsening / temperature)
+    SimulatedAnnealing,
+    // Worse networks are accepted if the relative worsening is below the temperature (threshold)
+    ThresholdAccepting,
+    // Accepted if not worse than the current error late_acceptance_length steps ago
+    LateAcceptance,
+}
+
+// Acceptance state of one network, it is kept over all batch iterations
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcceptanceState {
+    policy: AcceptancePolicy,
+    // Temperature for annealing, threshold for threshold accepting
+    temperature: f64,
+    cooling_rate: f64,
+    min_temperature: f64,
+    late_acceptance_length: usize,
+    // Current errors of the last late_acceptance_length steps (ring buffer)
+    recent_errors: Vec<f64>,
+    num_of_steps: usize,
+}
+
+// (new - current) / current, the batch errors of different problems have very different scales.
+// A current error of 0 can not be scaled, then the absolute worsening is used.
+fn relative_worsening(new_error: f64, current_error: f64) -> f64 {
+    if current_error > 0.0 {
+        (new_error - current_error) / current_error
+    } else {
+        new_error - current_error
+    }
+}
+
+impl AcceptanceState {
+    pub fn new(configuration: &DriverConfiguration) -> AcceptanceState {
+        AcceptanceState {
+            policy: configuration.acceptance,
+            temperature: configuration.initial_temperature,
+            cooling_rate: configuration.cooling_rate,
+            min_temperature: configuration.min_temperature,
+            late_acceptance_length: configuration.late_acceptance_length,
+            recent_errors: Vec::new(),
+            num_of_steps: 0,
+        }
+    }
+
+    pub fn validate(configuration: &DriverConfiguration) -> Result<(), ConfigurationError> {
+        let mut errors = Vec::new();
+
+        if !configuration.initial_temperature.is_finite() || configuration.initial_temperature < 0.0 {
+            errors.push(format!("initial_temperature must be finite and >= 0, given: {}", configuration.initial_temperature));
+        }
+
+        if !(configuration.cooling_rate > 0.0 && configuration.cooling_rate <= 1.0) {
+            errors.push(format!("cooling_rate must be in (0, 1], given: {}", configuration.cooling_rate));
+        }
+
+        if !configuration.min_temperature.is_finite() || configuration.min_temperature < 0.0 {
+            errors.push(format!("min_temperature must be finite and >= 0, given: {}", configuration.min_temperature));
+        }
+
+        if configuration.late_acceptance_length == 0 {
+            errors.push("late_acceptance_length must be > 0".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigurationError { errors })
+        }
+    }
+
+    pub fn policy(&self) -> AcceptancePolicy {
+        self.policy
+    }
+
+    pub fn is_hill_climbing(&self) -> bool {
+        self.policy == AcceptancePolicy::HillClimbing
+    }
+
+    // None if the policy has no temperature
+    pub fn temperature(&self) -> Option<f64> {
+        match self.policy {
+            AcceptancePolicy::SimulatedAnnealing | AcceptancePolicy::ThresholdAccepting => Some(self.temperature),
+            AcceptancePolicy::HillClimbing | AcceptancePolicy::LateAcceptance => None,
+        }
+    }
+
+    pub fn set_temperature(&mut self, temperature: f64) {
+        self.temperature = temperature;
+    }
+
+    // Called at the start of Network::optimize(), the errors of the previous batch can not be compared
+    // with the errors of the new batch
+    pub fn start_batch(&mut self, current_error: f64) {
+        if self.policy == AcceptancePolicy::LateAcceptance {
+            self.recent_errors = vec![current_error; self.late_acceptance_length];
+            self.num_of_steps = 0;
+        }
+    }
+
+    // Only called for mutations that did not improve the current error
+    pub fn accept<R: Rng>(&mut self, new_error: f64, current_error: f64, rng: &mut R) -> bool {
+        match self.policy {
+            AcceptancePolicy::HillClimbing => false,
+            AcceptancePolicy::SimulatedAnnealing => {
+                self.temperature > 0.0 && rng.gen::<f64>() < (-relative_worsening(new_error, current_error) / self.temperature).exp()
+            }
+            AcceptancePolicy::ThresholdAccepting => relative_worsening(new_error, current_error) <= self.temperature,
+            AcceptancePolicy::LateAcceptance => {
+                let index = self.num_of_steps % self.late_acceptance_length;
+                new_error <= self.recent_errors.get(index).cloned().unwrap_or(current_error)
+            }
+        }
+    }
+
+    // Called once after each mutation with the current error after the decision
+    pub fn update(&mut self, current_error: f64) {
+        if self.policy == AcceptancePolicy::LateAcceptance {
+            let index = self.num_of_steps % self.late_acceptance_length;
+            if index < self.recent_errors.len() {
+                self.recent_errors[index] = current_error;
+            }
+            self.num_of_steps += 1;
+        }
+    }
+
+    // Called at the end of Network::optimize(), all mutations of one call see the same temperature
+    pub fn cool(&mut self) {
+        if let AcceptancePolicy::SimulatedAnnealing | AcceptancePolicy::ThresholdAccepting = self.policy {
+            self.temperature = (self.temperature * self.cooling_rate).max(self.min_temperature);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::{XorShiftRng, SeedableRng};
+    use toml;
+
+    fn state(policy: AcceptancePolicy) -> AcceptanceState {
+        let mut configuration: DriverConfiguration = toml::from_str("").unwrap();
+        configuration.acceptance = policy;
+        configuration.initial_temperature = 0.1;
+        configuration.cooling_rate = 0.5;
+        configuration.min_temperature = 0.01;
+        configuration.late_acceptance_length = 2;
+        AcceptanceState::new(&configuration)
+    }
+
+    #[test]
+    fn temperature_cools_down_to_minimum() {
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+        let mut threshold = state(AcceptancePolicy::ThresholdAccepting);
+
+        assert!(threshold.accept(1.05, 1.0, &mut rng));
+        assert!(!threshold.accept(1.2, 1.0, &mut rng));
+        // Absolute worsening for a perfect current network
+        assert!(threshold.accept(0.05, 0.0, &mut rng));
+        assert!(!threshold.accept(0.2, 0.0, &mut rng));
+
+        for _ in 0..10 {
+            threshold.update(1.0);
+        }
+        // Mutations do not cool
+        assert_eq!(threshold.temperature(), Some(0.1));
+
+        for _ in 0..10 {
+            threshold.cool();
+        }
+        assert_eq!(threshold.temperature(), Some(0.01));
+        assert!(!threshold.accept(1.05, 1.0, &mut rng));
+
+        let mut hill_climbing = state(AcceptancePolicy::HillClimbing);
+        assert!(!hill_climbing.accept(1.0, 1.0, &mut rng));
+        assert_eq!(hill_climbing.temperature(), None);
+    }
+
+    #[test]
+    fn default_schedule_accepts_worse_candidates() {
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+        let mut configuration: DriverConfiguration = toml::from_str("").unwrap();
+        configuration.acceptance = AcceptancePolicy::SimulatedAnnealing;
+        let mut annealing = AcceptanceState::new(&configuration);
+
+        // Halfway through a training run with the default number of iterations
+        for _ in 0..(configuration.num_of_batch_iterations / 2) * configuration.num_of_iterations {
+            annealing.cool();
+        }
+
+        let num_of_accepted = (0..100).filter(|_| annealing.accept(1.001, 1.0, &mut rng)).count();
+        assert!(num_of_accepted > 0 && num_of_accepted < 100, "Accepted: {}", num_of_accepted);
+    }
+
+    #[test]
+    fn late_acceptance_compares_with_old_errors() {
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+        let mut late = state(AcceptancePolicy::LateAcceptance);
+
+        late.start_batch(2.0);
+        late.update(1.0);
+        late.update(1.0);
+        // Both old errors were replaced by 1.0
+        assert!(!late.accept(1.5, 1.0, &mut rng));
+
+        late.start_batch(2.0);
+        assert!(late.accept(1.5, 1.0, &mut rng));
+    }
+}
@@ -0,0 +1,226 @@
+use failure::Error;
+use fnv::FnvHashSet;
+
+use node::{Node};
+use property::{Property};
+
+// Describes how a network trained for one shape is used for a different number of inputs / outputs.
+//
+// Connection indices (and output indices) address the value vector of a network:
+// the first num_of_input_nodes values are the inputs, followed by one value per node.
+// Changing the number of inputs therefore changes the index of every node value, too.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShapeMapping {
+    pub num_of_old_inputs: usize,
+    pub num_of_new_inputs: usize,
+    // One entry for each old input: the new input it is read from or None if it is dropped.
+    // New inputs that no old input maps to are added inputs.
+    pub input_map: Vec<Option<usize>>,
+    // One entry for each new output: the old output it takes over or None for an added output.
+    // Each added output gets its own new node.
+    pub output_map: Vec<Option<usize>>,
+    // Connect added inputs with weight 0.0 to the nodes that feed the outputs,
+    // so mutation can pick them up without changing the current behaviour.
+    #[serde(default)]
+    pub connect_added_inputs: bool,
+}
+
+impl ShapeMapping {
+    // Keeps the first min(old, new) inputs and outputs in place, drops or adds the rest
+    pub fn identity(num_of_old_inputs: usize, num_of_old_outputs: usize, num_of_new_inputs: usize, num_of_new_outputs: usize) -> ShapeMapping {
+        let input_map = (0..num_of_old_inputs).map(|index| {
+            if index < num_of_new_inputs { Some(index) } else { None }
+        }).collect();
+
+        let output_map = (0..num_of_new_outputs).map(|index| {
+            if index < num_of_old_outputs { Some(index) } else { None }
+        }).collect();
+
+        ShapeMapping {
+            num_of_old_inputs,
+            num_of_new_inputs,
+            input_map,
+            output_map,
+            connect_added_inputs: true,
+        }
+    }
+
+    pub fn added_inputs(&self) -> Vec<usize> {
+        (0..self.num_of_new_inputs).filter(|index| !self.input_map.contains(&Some(*index))).collect()
+    }
+
+    fn check(&self, property: &Property) -> Result<(), Error> {
+        if self.input_map.len() != self.num_of_old_inputs {
+            bail!("Input map has {} entries, but the network has {} inputs", self.input_map.len(), self.num_of_old_inputs);
+        }
+
+        let mut used_inputs = FnvHashSet::default();
+
+        for new_index in self.input_map.iter().filter_map(|index| *index) {
+            if new_index >= self.num_of_new_inputs {
+                bail!("Input map target {} is out of range, only {} new inputs", new_index, self.num_of_new_inputs);
+            }
+
+            if !used_inputs.insert(new_index) {
+                bail!("Input map target {} is used more than once", new_index);
+            }
+        }
+
+        for old_index in self.output_map.iter().filter_map(|index| *index) {
+            if old_index >= property.output_indices.len() {
+                bail!("Output map source {} is out of range, network has only {} outputs", old_index, property.output_indices.len());
+            }
+        }
+
+        let max_connection_index = self.num_of_old_inputs + property.nodes.len();
+
+        for (node_index, node) in property.nodes.iter().enumerate() {
+            if let Some(index) = node.max_connection_index() {
+                if index >= max_connection_index {
+                    bail!("Node {} has connection index {}, but the network has only {} values", node_index, index, max_connection_index);
+                }
+            }
+        }
+
+        for index in &property.output_indices {
+            if *index >= max_connection_index {
+                bail!("Output index {} is out of range, the network has only {} values", index, max_connection_index);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Maps a value index of the old network to the value index of the adapted network
+    fn map_index(&self, index: usize) -> Option<usize> {
+        if index < self.num_of_old_inputs {
+            self.input_map[index]
+        } else {
+            Some(index - self.num_of_old_inputs + self.num_of_new_inputs)
+        }
+    }
+}
+
+impl Property {
+    // Rewrites the connection and output indices of a trained network for a new shape.
+    // Connections that read dropped inputs are removed, everything else keeps reading the same value
+    // as before, so the learned sub-structure is preserved.
+    pub fn adapt(&self, mapping: &ShapeMapping) -> Result<Property, Error> {
+        mapping.check(self)?;
+
+        let mut nodes = self.nodes.clone();
+
+        for node in &mut nodes {
+            node.remap_connections(|index| mapping.map_index(index));
+
+            if node.num_of_connections() == 0 {
+                // Every node needs at least one connection
+                node.add_connection(0, 0.0);
+            }
+        }
+
+        let mut output_indices = Vec::with_capacity(mapping.output_map.len());
+
+        for old_output in &mapping.output_map {
+            let new_index = old_output.and_then(|old_output| mapping.map_index(self.output_indices[old_output]));
+
+            match new_index {
+                Some(index) => output_indices.push(index),
+                None => {
+                    // Added output or output that read a dropped input: give it its own node
+                    let mut node = Node::new(0.0);
+                    node.add_connection(0, 0.0);
+                    output_indices.push(mapping.num_of_new_inputs + nodes.len());
+                    nodes.push(node);
+                }
+            }
+        }
+
+        if mapping.connect_added_inputs {
+            for input_index in mapping.added_inputs() {
+                for output_index in &output_indices {
+                    if *output_index < mapping.num_of_new_inputs {
+                        continue
+                    }
+
+                    let node = &mut nodes[*output_index - mapping.num_of_new_inputs];
+
+                    if !node.has_connection(input_index) {
+                        node.add_connection(input_index, 0.0);
+                    }
+                }
+            }
+        }
+
+        Ok(Property {
+            nodes,
+            output_indices,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use toml;
+
+    fn xor02() -> Property {
+        toml::from_str(include_str!("trained_networks/xor02.toml")).unwrap()
+    }
+
+    #[test]
+    fn identity_keeps_property() {
+        let property = xor02();
+        let mapping = ShapeMapping::identity(2, 1, 2, 1);
+
+        assert_eq!(property.adapt(&mapping).unwrap(), property);
+    }
+
+    #[test]
+    fn added_input_shifts_node_references() {
+        let property = xor02();
+        let mapping = ShapeMapping {
+            num_of_old_inputs: 2,
+            num_of_new_inputs: 3,
+            input_map: vec![Some(1), Some(2)],
+            output_map: vec![Some(0)],
+            connect_added_inputs: false,
+        };
+
+        let adapted = property.adapt(&mapping).unwrap();
+
+        // Old input 0 -> new input 1, old input 1 -> new input 2, old nodes 2, 3 -> 3, 4
+        assert!(adapted.nodes[0].has_connection(1));
+        assert!(adapted.nodes[0].has_connection(2));
+        assert!(adapted.nodes[0].has_connection(3));
+        assert!(adapted.nodes[0].has_connection(4));
+        assert!(!adapted.nodes[0].has_connection(0));
+        assert_eq!(adapted.output_indices, vec![4]);
+    }
+
+    #[test]
+    fn added_output_gets_new_node() {
+        let property = xor02();
+        let mapping = ShapeMapping::identity(2, 1, 2, 2);
+
+        let adapted = property.adapt(&mapping).unwrap();
+
+        assert_eq!(adapted.nodes.len(), 3);
+        assert_eq!(adapted.output_indices, vec![3, 4]);
+    }
+
+    #[test]
+    fn invalid_mapping_is_rejected() {
+        let property = xor02();
+        let mapping = ShapeMapping {
+            num_of_old_inputs: 2,
+            num_of_new_inputs: 2,
+            input_map: vec![Some(1), Some(1)],
+            output_map: vec![Some(0)],
+            connect_added_inputs: false,
+        };
+
+        assert!(property.adapt(&mapping).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use property::{Property};
+
+// What a value index (connection index or output index) refers to
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ValueSource {
+    Input(usize),
+    Node(usize),
+    // Index is outside of the value vector
+    Invalid(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphAnalysis {
+    pub num_of_input_nodes: usize,
+    pub num_of_nodes: usize,
+    // Nodes that have an influence on at least one output
+    pub reachable_nodes: Vec<usize>,
+    // Nodes that can be removed without changing the outputs
+    pub dead_nodes: Vec<usize>,
+    pub used_inputs: Vec<usize>,
+    pub unused_inputs: Vec<usize>,
+}
+
+impl GraphAnalysis {
+    pub fn has_dead_nodes(&self) -> bool {
+        !self.dead_nodes.is_empty()
+    }
+
+    pub fn is_reachable(&self, node_index: usize) -> bool {
+        self.reachable_nodes.binary_search(&node_index).is_ok()
+    }
+}
+
+pub fn value_source(num_of_input_nodes: usize, num_of_nodes: usize, index: usize) -> ValueSource {
+    if index < num_of_input_nodes {
+        ValueSource::Input(index)
+    } else if index < num_of_input_nodes + num_of_nodes {
+        ValueSource::Node(index - num_of_input_nodes)
+    } else {
+        ValueSource::Invalid(index)
+    }
+}
+
+impl Property {
+    pub fn value_source(&self, num_of_input_nodes: usize, index: usize) -> ValueSource {
+        value_source(num_of_input_nodes, self.nodes.len(), index)
+    }
+
+    // Walks the connections backwards, starting at the outputs
+    pub fn analyze(&self, num_of_input_nodes: usize) -> GraphAnalysis {
+        let num_of_nodes = self.nodes.len();
+        let mut used_inputs = vec![false; num_of_input_nodes];
+        let mut reachable = vec![false; num_of_nodes];
+        let mut stack: Vec<usize> = self.output_indices.clone();
+
+        while let Some(index) = stack.pop() {
+            match self.value_source(num_of_input_nodes, index) {
+                ValueSource::Input(input_index) => {
+                    used_inputs[input_index] = true;
+                }
+                ValueSource::Node(node_index) => {
+                    if !reachable[node_index] {
+                        reachable[node_index] = true;
+                        stack.extend(self.nodes[node_index].connection_indices());
+                    }
+                }
+                ValueSource::Invalid(_) => {}
+            }
+        }
+
+        GraphAnalysis {
+            num_of_input_nodes,
+            num_of_nodes,
+            reachable_nodes: (0..num_of_nodes).filter(|index| reachable[*index]).collect(),
+            dead_nodes: (0..num_of_nodes).filter(|index| !reachable[*index]).collect(),
+            used_inputs: (0..num_of_input_nodes).filter(|index| used_inputs[*index]).collect(),
+            unused_inputs: (0..num_of_input_nodes).filter(|index| !used_inputs[*index]).collect(),
+        }
+    }
+
+    // Removes all unfrozen nodes that have no influence on the outputs.
+    // Nodes that are read by a frozen node stay, so the frozen nodes keep their connections.
+    // Returns the number of removed nodes.
+    pub fn prune_dead_nodes(&mut self, num_of_input_nodes: usize) -> usize {
+        let analysis = self.analyze(num_of_input_nodes);
+        let mut num_of_removed_nodes = 0;
+
+        // Remove from the back, so the indices of the remaining dead nodes stay valid
+        for node_index in analysis.dead_nodes.into_iter().rev() {
+            if self.nodes.len() == 1 {
+                break
+            }
+
+            let value_index = num_of_input_nodes + node_index;
+            if self.nodes[node_index].is_frozen() || self.nodes.iter().any(|node| node.is_frozen() && node.has_connection(value_index)) {
+                continue
+            }
+
+            self.remove_node(num_of_input_nodes, node_index);
+            num_of_removed_nodes += 1;
+        }
+
+        num_of_removed_nodes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use toml;
+
+    fn xor02() -> Property {
+        toml::from_str(include_str!("trained_networks/xor02.toml")).unwrap()
+    }
+
+    #[test]
+    fn output_reading_an_input_makes_all_nodes_dead() {
+        let mut property = xor02();
+        property.output_indices = vec![0];
+        let analysis = property.analyze(2);
+
+        assert_eq!(analysis.reachable_nodes, Vec::<usize>::new());
+        assert_eq!(analysis.dead_nodes, vec![0, 1]);
+        assert_eq!(analysis.used_inputs, vec![0]);
+        assert_eq!(analysis.unused_inputs, vec![1]);
+    }
+
+    #[test]
+    fn reachability_follows_connections() {
+        let mut property = xor02();
+        // Node 1 (value index 3) reads node 0 (value index 2), which reads both inputs
+        property.output_indices = vec![3];
+
+        let analysis = property.analyze(2);
+
+        assert_eq!(analysis.reachable_nodes, vec![0, 1]);
+        assert!(!analysis.has_dead_nodes());
+        assert!(analysis.unused_inputs.is_empty());
+        assert_eq!(property.value_source(2, 3), ValueSource::Node(1));
+        assert_eq!(property.value_source(2, 4), ValueSource::Invalid(4));
+    }
+
+    #[test]
+    fn prune_keeps_outputs_unchanged() {
+        let mut property = xor02();
+        property.output_indices = vec![3];
+        property.nodes[1].remap_connections(|_| Some(0));
+        // Node 1 only reads input 0 now, so node 0 is dead
+        assert_eq!(property.analyze(2).dead_nodes, vec![0]);
+
+        assert_eq!(property.prune_dead_nodes(2), 1);
+        assert_eq!(property.nodes.len(), 1);
+        assert_eq!(property.output_indices, vec![2]);
+    }
+
+    #[test]
+    fn prune_keeps_connections_of_frozen_nodes() {
+        let mut property = xor02();
+        property.output_indices = vec![0];
+        // All nodes are dead, node 1 reads node 0
+        assert_eq!(property.analyze(2).dead_nodes, vec![0, 1]);
+        property.freeze_nodes(1, 2);
+        let frozen_node = property.nodes[1].clone();
+
+        assert_eq!(property.prune_dead_nodes(2), 0);
+        assert_eq!(property.nodes[1], frozen_node);
+    }
+}
@@ -0,0 +1,18 @@
+// Worker process for Driver::train_distributed()
+// Usage: gronn-worker [address], default address: 127.0.0.1:7878
+
+extern crate gronn;
+
+use std::env;
+use std::process;
+
+fn main() {
+    let address = env::args().nth(1).unwrap_or_else(|| "127.0.0.1:7878".to_string());
+
+    println!("gronn-worker listening on {}", address);
+
+    if let Err(e) = gronn::distributed::run_worker(address.as_str()) {
+        eprintln!("gronn-worker: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,318 @@
+// Local versions of classic control tasks for the Environment API.
+// All of them have one action, observations are scaled to roughly [-1, 1].
+// With EpisodeRunner the cost is the number of steps without the best reward.
+
+use std::f64::consts::PI;
+
+use rand::{Rng, XorShiftRng};
+
+use environment::{Environment, Step};
+
+const TRACK_LIMIT: f64 = 2.4;
+
+// Balance a pole on a cart by pushing the cart left (action <= 0) or right (action > 0).
+// Reward 1 for every step the pole stays up.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CartPole {
+    pub x: f64,
+    pub x_dot: f64,
+    pub theta: f64,
+    pub theta_dot: f64,
+}
+
+impl CartPole {
+    const GRAVITY: f64 = 9.8;
+    const MASS_CART: f64 = 1.0;
+    const MASS_POLE: f64 = 0.1;
+    // Half of the pole length
+    const LENGTH: f64 = 0.5;
+    const FORCE: f64 = 10.0;
+    const TAU: f64 = 0.02;
+    // 12 degrees
+    const THETA_LIMIT: f64 = 12.0 * PI / 180.0;
+
+    pub fn new() -> CartPole {
+        CartPole::default()
+    }
+
+    pub fn has_failed(&self) -> bool {
+        self.x.abs() > TRACK_LIMIT || self.theta.abs() > Self::THETA_LIMIT
+    }
+}
+
+impl Environment for CartPole {
+    fn num_of_observations(&self) -> usize {4}
+
+    fn num_of_actions(&self) -> usize {1}
+
+    fn max_reward_per_step(&self) -> f64 {1.0}
+
+    fn reset(&mut self, rng: &mut XorShiftRng) {
+        self.x = rng.gen_range(-0.05, 0.05);
+        self.x_dot = rng.gen_range(-0.05, 0.05);
+        self.theta = rng.gen_range(-0.05, 0.05);
+        self.theta_dot = rng.gen_range(-0.05, 0.05);
+    }
+
+    fn observe(&self) -> Vec<f64> {
+        vec![self.x / TRACK_LIMIT, self.x_dot / 2.0, self.theta / Self::THETA_LIMIT, self.theta_dot / 2.0]
+    }
+
+    fn step(&mut self, action: &[f64]) -> Step {
+        let force = if action[0] > 0.0 { Self::FORCE } else { -Self::FORCE };
+        let total_mass = Self::MASS_CART + Self::MASS_POLE;
+        let pole_mass_length = Self::MASS_POLE * Self::LENGTH;
+        let (sin_theta, cos_theta) = self.theta.sin_cos();
+
+        let temp = (force + pole_mass_length * self.theta_dot * self.theta_dot * sin_theta) / total_mass;
+        let theta_acc = (Self::GRAVITY * sin_theta - cos_theta * temp) /
+            (Self::LENGTH * ((4.0 / 3.0) - (Self::MASS_POLE * cos_theta * cos_theta / total_mass)));
+        let x_acc = temp - (pole_mass_length * theta_acc * cos_theta / total_mass);
+
+        self.x += Self::TAU * self.x_dot;
+        self.x_dot += Self::TAU * x_acc;
+        self.theta += Self::TAU * self.theta_dot;
+        self.theta_dot += Self::TAU * theta_acc;
+
+        let done = self.has_failed();
+
+        Step {
+            reward: if done { 0.0 } else { 1.0 },
+            done,
+        }
+    }
+}
+
+// Drive an underpowered car up a hill, action in [-1, 1] is the throttle.
+// Reward -1 for every step until the car reaches the goal.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MountainCar {
+    pub position: f64,
+    pub velocity: f64,
+}
+
+impl MountainCar {
+    const MIN_POSITION: f64 = -1.2;
+    const MAX_POSITION: f64 = 0.6;
+    const MAX_VELOCITY: f64 = 0.07;
+    const GOAL_POSITION: f64 = 0.5;
+    const POWER: f64 = 0.0015;
+
+    pub fn new() -> MountainCar {
+        MountainCar::default()
+    }
+
+    pub fn has_reached_goal(&self) -> bool {
+        self.position >= Self::GOAL_POSITION
+    }
+}
+
+impl Environment for MountainCar {
+    fn num_of_observations(&self) -> usize {2}
+
+    fn num_of_actions(&self) -> usize {1}
+
+    // Reaching the goal
+    fn max_reward_per_step(&self) -> f64 {0.0}
+
+    fn reset(&mut self, rng: &mut XorShiftRng) {
+        self.position = rng.gen_range(-0.6, -0.4);
+        self.velocity = 0.0;
+    }
+
+    fn observe(&self) -> Vec<f64> {
+        vec![(self.position + 0.3) / 0.9, self.velocity / Self::MAX_VELOCITY]
+    }
+
+    fn step(&mut self, action: &[f64]) -> Step {
+        let throttle = if action[0].is_nan() { 0.0 } else { action[0].clamp(-1.0, 1.0) };
+
+        self.velocity += (throttle * Self::POWER) - (0.0025 * (3.0 * self.position).cos());
+        self.velocity = self.velocity.clamp(-Self::MAX_VELOCITY, Self::MAX_VELOCITY);
+        self.position += self.velocity;
+        self.position = self.position.clamp(Self::MIN_POSITION, Self::MAX_POSITION);
+
+        if self.position <= Self::MIN_POSITION && self.velocity < 0.0 {
+            // Hit the left wall
+            self.velocity = 0.0;
+        }
+
+        let done = self.has_reached_goal();
+
+        Step {
+            reward: if done { 0.0 } else { -1.0 },
+            done,
+        }
+    }
+}
+
+// Balance two poles of different length on the same cart, action in [-1, 1] is the force.
+// Without velocities in the observation (hide_velocities) the network has to estimate them
+// with its recurrent connections. Reward 1 for every step both poles stay up.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DoublePole {
+    pub x: f64,
+    pub x_dot: f64,
+    pub theta1: f64,
+    pub theta1_dot: f64,
+    pub theta2: f64,
+    pub theta2_dot: f64,
+    pub hide_velocities: bool,
+}
+
+impl DoublePole {
+    // Sign convention of the original benchmark
+    const GRAVITY: f64 = -9.8;
+    const MASS_CART: f64 = 1.0;
+    const MASS_POLE1: f64 = 0.1;
+    const MASS_POLE2: f64 = 0.01;
+    // Half of the pole lengths
+    const LENGTH1: f64 = 0.5;
+    const LENGTH2: f64 = 0.05;
+    const FORCE: f64 = 10.0;
+    const TAU: f64 = 0.01;
+    // 36 degrees
+    const THETA_LIMIT: f64 = 36.0 * PI / 180.0;
+    // Start angle of the long pole: 4.5 degrees
+    const THETA1_START: f64 = 4.5 * PI / 180.0;
+
+    pub fn new() -> DoublePole {
+        DoublePole::default()
+    }
+
+    pub fn without_velocities() -> DoublePole {
+        DoublePole {
+            hide_velocities: true,
+            ..DoublePole::default()
+        }
+    }
+
+    pub fn has_failed(&self) -> bool {
+        self.x.abs() > TRACK_LIMIT || self.theta1.abs() > Self::THETA_LIMIT || self.theta2.abs() > Self::THETA_LIMIT
+    }
+
+    // Force and mass of a pole, seen from the cart
+    fn pole_effect(mass: f64, length: f64, theta: f64, theta_dot: f64) -> (f64, f64) {
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let force = (mass * length * theta_dot * theta_dot * sin_theta) + (0.75 * mass * cos_theta * Self::GRAVITY * sin_theta);
+        let effective_mass = mass * (1.0 - (0.75 * cos_theta * cos_theta));
+
+        (force, effective_mass)
+    }
+
+    fn integrate(&mut self, force: f64) {
+        let (force1, mass1) = Self::pole_effect(Self::MASS_POLE1, Self::LENGTH1, self.theta1, self.theta1_dot);
+        let (force2, mass2) = Self::pole_effect(Self::MASS_POLE2, Self::LENGTH2, self.theta2, self.theta2_dot);
+
+        let x_acc = (force + force1 + force2) / (Self::MASS_CART + mass1 + mass2);
+        let theta1_acc = -0.75 * ((x_acc * self.theta1.cos()) + (Self::GRAVITY * self.theta1.sin())) / Self::LENGTH1;
+        let theta2_acc = -0.75 * ((x_acc * self.theta2.cos()) + (Self::GRAVITY * self.theta2.sin())) / Self::LENGTH2;
+
+        self.x += Self::TAU * self.x_dot;
+        self.x_dot += Self::TAU * x_acc;
+        self.theta1 += Self::TAU * self.theta1_dot;
+        self.theta1_dot += Self::TAU * theta1_acc;
+        self.theta2 += Self::TAU * self.theta2_dot;
+        self.theta2_dot += Self::TAU * theta2_acc;
+    }
+}
+
+impl Environment for DoublePole {
+    fn num_of_observations(&self) -> usize {
+        if self.hide_velocities { 3 } else { 6 }
+    }
+
+    fn num_of_actions(&self) -> usize {1}
+
+    fn max_reward_per_step(&self) -> f64 {1.0}
+
+    fn reset(&mut self, rng: &mut XorShiftRng) {
+        self.x = 0.0;
+        self.x_dot = 0.0;
+        self.theta1 = Self::THETA1_START + rng.gen_range(-0.01, 0.01);
+        self.theta1_dot = 0.0;
+        self.theta2 = 0.0;
+        self.theta2_dot = 0.0;
+    }
+
+    fn observe(&self) -> Vec<f64> {
+        if self.hide_velocities {
+            vec![self.x / TRACK_LIMIT, self.theta1 / Self::THETA_LIMIT, self.theta2 / Self::THETA_LIMIT]
+        } else {
+            vec![self.x / TRACK_LIMIT, self.x_dot / 2.0, self.theta1 / Self::THETA_LIMIT, self.theta1_dot / 2.0,
+                self.theta2 / Self::THETA_LIMIT, self.theta2_dot / 2.0]
+        }
+    }
+
+    fn step(&mut self, action: &[f64]) -> Step {
+        let force = if action[0].is_nan() { 0.0 } else { action[0].clamp(-1.0, 1.0) * Self::FORCE };
+
+        // Two integration steps per action, like in the original benchmark
+        self.integrate(force);
+        self.integrate(force);
+
+        let done = self.has_failed();
+
+        Step {
+            reward: if done { 0.0 } else { 1.0 },
+            done,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::{SeedableRng};
+
+    fn rng() -> XorShiftRng {
+        XorShiftRng::from_seed([1; 16])
+    }
+
+    fn steps_until_done<E: Environment>(environment: &mut E, action: f64, max_steps: usize) -> usize {
+        environment.reset(&mut rng());
+
+        for step in 0..max_steps {
+            if environment.step(&[action]).done {
+                return step + 1
+            }
+        }
+
+        max_steps
+    }
+
+    #[test]
+    fn pole_falls_without_control() {
+        assert!(steps_until_done(&mut CartPole::new(), 1.0, 500) < 500);
+        assert!(steps_until_done(&mut DoublePole::new(), 0.0, 500) < 500);
+    }
+
+    #[test]
+    fn mountain_car_needs_momentum() {
+        // Full throttle is not enough to drive up the hill directly
+        assert_eq!(steps_until_done(&mut MountainCar::new(), 1.0, 200), 200);
+
+        // Swing back and forth with the velocity
+        let mut car = MountainCar::new();
+        car.reset(&mut rng());
+        let mut num_of_steps = 0;
+        while !car.has_reached_goal() && num_of_steps < 500 {
+            let throttle = if car.velocity < 0.0 { -1.0 } else { 1.0 };
+            car.step(&[throttle]);
+            num_of_steps += 1;
+        }
+        assert!(car.has_reached_goal());
+    }
+
+    #[test]
+    fn observations_match_num_of_observations() {
+        let environments: Vec<Box<dyn Environment>> = vec![Box::new(CartPole::new()), Box::new(MountainCar::new()),
+            Box::new(DoublePole::new()), Box::new(DoublePole::without_velocities())];
+
+        for mut environment in environments {
+            environment.reset(&mut rng());
+            assert_eq!(environment.observe().len(), environment.num_of_observations());
+        }
+    }
+}
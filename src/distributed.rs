@@ -0,0 +1,639 @@
+// Coordinator / worker protocol for training over several processes.
+// Every message is one line of JSON over a TCP connection.
+
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use failure::Error;
+use rayon::prelude::*;
+use serde_json;
+
+use driver::{DriverConfiguration, TrainingData, optimize_network};
+use fitness::{Objective};
+use network::{Network};
+use population::{NetworkRecord};
+use property::{Property};
+use stopping::{CancellationToken, TrainingBudget};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    // Sent once per training run, needed by the other requests
+    SetData {
+        configuration: Box<DriverConfiguration>,
+        training_data: TrainingData,
+    },
+    // Error of the property for the given entries of the training data
+    Evaluate {
+        job_id: usize,
+        property: Property,
+        indices: Vec<usize>,
+    },
+    // Runs the inner loop of Driver::train() for one network and one batch
+    Optimize {
+        job_id: usize,
+        record: Box<NetworkRecord>,
+        indices: Vec<usize>,
+        // What is left of the budget of the coordinator
+        #[serde(default)]
+        max_seconds: Option<f64>,
+        #[serde(default)]
+        max_evaluations: Option<usize>,
+    },
+    // Stops the running Optimize job at its next safe point, the job is answered as usual
+    Cancel,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+    Ready,
+    Evaluated {
+        job_id: usize,
+        error: f64,
+    },
+    Optimized {
+        job_id: usize,
+        record: Box<NetworkRecord>,
+        num_of_evaluations: usize,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+// The error on a batch of labelled data
+struct BatchObjective<'a> {
+    training_data: &'a TrainingData,
+    indices: &'a [usize],
+}
+
+impl<'a> Objective for BatchObjective<'a> {
+    fn error(&self, network: &mut Network) -> f64 {
+        network.calculate_batch_and_error(self.indices, self.training_data)
+    }
+
+    fn full_error(&self, network: &mut Network) -> f64 {
+        network.calculate_total_error(self.training_data)
+    }
+}
+
+fn send<W: Write, T: ::serde::Serialize>(writer: &mut W, message: &T) -> Result<(), Error> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+fn receive<R: BufRead, T: ::serde::de::DeserializeOwned>(reader: &mut R) -> Result<T, Error> {
+    let mut line = String::new();
+
+    if reader.read_line(&mut line)? == 0 {
+        bail!("Connection closed");
+    }
+
+    Ok(serde_json::from_str(&line)?)
+}
+
+// The lineage history is not needed by the workers and grows with every batch
+fn job_record(network: &Network) -> NetworkRecord {
+    let mut record = network.to_record();
+    record.state.lineage = None;
+    record
+}
+
+// Number of error calculations and the trained network
+fn optimize_record(configuration: &DriverConfiguration, training_data: &TrainingData, record: NetworkRecord, indices: &[usize], budget: &TrainingBudget) -> (NetworkRecord, usize) {
+    let mut network = Network::from_record(configuration.clone(), record);
+    let objective = BatchObjective { training_data, indices };
+
+    optimize_network(&mut network, &objective, budget, configuration.num_of_iterations);
+
+    (network.to_record(), budget.evaluations())
+}
+
+fn evaluate_property(configuration: &DriverConfiguration, training_data: &TrainingData, property: Property, indices: &[usize]) -> f64 {
+    let mut network = Network::new_with_property(configuration.clone(), property, "evaluate");
+    let objective = BatchObjective { training_data, indices };
+
+    objective.error(&mut network)
+}
+
+fn check_indices(training_data: &TrainingData, indices: &[usize]) -> Result<(), Error> {
+    let len = training_data.provided_input.len();
+
+    if let Some(index) = indices.iter().find(|index| **index >= len) {
+        bail!("Index {} out of range, number of entries: {}", index, len);
+    }
+
+    Ok(())
+}
+
+fn handle_request(request: Request, data: &mut Option<(DriverConfiguration, TrainingData)>, token: &CancellationToken) -> Result<Response, Error> {
+    if let Request::SetData { configuration, training_data } = request {
+        configuration.validate()?;
+        *data = Some((*configuration, training_data));
+        return Ok(Response::Ready)
+    }
+
+    let (configuration, training_data) = match *data {
+        Some((ref configuration, ref training_data)) => (configuration, training_data),
+        None => bail!("No training data, SetData must be the first request"),
+    };
+
+    match request {
+        Request::SetData { .. } | Request::Cancel => unreachable!(),
+        Request::Evaluate { job_id, property, indices } => {
+            check_indices(training_data, &indices)?;
+            let error = evaluate_property(configuration, training_data, property, &indices);
+            Ok(Response::Evaluated { job_id, error })
+        }
+        Request::Optimize { job_id, record, indices, max_seconds, max_evaluations } => {
+            check_indices(training_data, &indices)?;
+            let budget = TrainingBudget::new(max_seconds, max_evaluations, token.clone());
+            let (record, num_of_evaluations) = optimize_record(configuration, training_data, *record, &indices, &budget);
+            Ok(Response::Optimized { job_id, record: Box::new(record), num_of_evaluations })
+        }
+    }
+}
+
+// Serves one coordinator until it closes the connection.
+// Requests are read in their own thread, so that a Cancel reaches the running job.
+pub fn handle_connection(stream: TcpStream) -> Result<(), Error> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut token = CancellationToken::new();
+
+        loop {
+            match receive(&mut reader) {
+                Ok(Request::Cancel) => token.cancel(),
+                Ok(request) => {
+                    // Every request gets its own token, a Cancel only stops the job that was sent before it
+                    token = CancellationToken::new();
+                    if sender.send((request, token.clone())).is_err() {
+                        break;
+                    }
+                }
+                // Coordinator is done
+                Err(_) => break,
+            }
+        }
+    });
+
+    let mut data = None;
+
+    for (request, token) in receiver {
+        let response = handle_request(request, &mut data, &token).unwrap_or_else(|e| Response::Failed { message: e.to_string() });
+        send(&mut writer, &response)?;
+    }
+
+    Ok(())
+}
+
+// Every connection is handled in its own thread
+pub fn serve(listener: TcpListener) -> Result<(), Error> {
+    for stream in listener.incoming() {
+        let stream = stream?;
+
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream) {
+                error!("Worker connection failed: {}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+pub fn run_worker<A: ToSocketAddrs>(address: A) -> Result<(), Error> {
+    serve(TcpListener::bind(address)?)
+}
+
+// Why a worker did not return the result of a job
+#[derive(Debug)]
+enum JobError {
+    // The worker could not do the job, it is done locally and the worker gets the next one
+    Failed(String),
+    // The connection is broken or the answer does not fit, the worker is not used again
+    Connection(Error),
+}
+
+impl From<Error> for JobError {
+    fn from(e: Error) -> JobError {
+        JobError::Connection(e)
+    }
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JobError::Failed(ref message) => write!(f, "{}", message),
+            JobError::Connection(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct WorkerConnection {
+    address: String,
+    reader: BufReader<TcpStream>,
+    // Shared with the thread that sends Cancel requests
+    writer: Arc<Mutex<TcpStream>>,
+    alive: bool,
+}
+
+impl WorkerConnection {
+    fn connect(address: &str, timeout: Duration) -> Result<WorkerConnection, Error> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_nodelay(true)?;
+
+        Ok(WorkerConnection {
+            address: address.to_string(),
+            writer: Arc::new(Mutex::new(stream.try_clone()?)),
+            reader: BufReader::new(stream),
+            alive: true,
+        })
+    }
+
+    fn request(&mut self, request: &Request) -> Result<Response, JobError> {
+        send(&mut *self.writer.lock().unwrap(), request)?;
+
+        match receive(&mut self.reader)? {
+            Response::Failed { message } => Err(JobError::Failed(format!("Worker {} failed: {}", self.address, message))),
+            response => Ok(response),
+        }
+    }
+
+    fn mark_dead<E: fmt::Display>(&mut self, e: &E) {
+        warn!("Worker {} removed: {}", self.address, e);
+        self.alive = false;
+    }
+}
+
+// Connections to worker processes (see the gronn-worker binary).
+// Workers that fail are not used again, their jobs are done by other workers or locally.
+#[derive(Debug)]
+pub struct WorkerPool {
+    workers: Vec<WorkerConnection>,
+    data: Option<(DriverConfiguration, TrainingData)>,
+}
+
+impl WorkerPool {
+    // Addresses that can not be reached are skipped
+    pub fn connect(addresses: &[&str], timeout: Duration) -> WorkerPool {
+        let workers = addresses.iter().filter_map(|address| {
+            match WorkerConnection::connect(address, timeout) {
+                Ok(worker) => Some(worker),
+                Err(e) => {
+                    warn!("Could not connect to worker {}: {}", address, e);
+                    None
+                }
+            }
+        }).collect();
+
+        WorkerPool {
+            workers,
+            data: None,
+        }
+    }
+
+    pub fn num_of_workers(&self) -> usize {
+        self.workers.iter().filter(|worker| worker.alive).count()
+    }
+
+    // Sends the configuration and the training data to all workers
+    pub fn begin(&mut self, configuration: &DriverConfiguration, training_data: &TrainingData) {
+        let request = Request::SetData {
+            configuration: Box::new(configuration.clone()),
+            training_data: training_data.clone(),
+        };
+
+        for worker in self.workers.iter_mut().filter(|worker| worker.alive) {
+            match worker.request(&request) {
+                Ok(Response::Ready) => {}
+                Ok(response) => worker.mark_dead(&format_err!("Unexpected response: {:?}", response)),
+                Err(e) => worker.mark_dead(&e),
+            }
+        }
+
+        self.data = Some((configuration.clone(), training_data.clone()));
+    }
+
+    // Runs the jobs on all workers in parallel, returns the jobs that could not be done.
+    // A cancellation of the budget is passed on to the running jobs.
+    fn run_jobs<F, T>(&mut self, num_of_jobs: usize, budget: Option<&TrainingBudget>, job: F) -> Vec<Option<T>>
+        where F: Fn(&mut WorkerConnection, usize) -> Result<T, JobError> + Sync,
+              T: Send {
+        let queue = Mutex::new((0..num_of_jobs).rev().collect::<Vec<usize>>());
+        let results = Mutex::new((0..num_of_jobs).map(|_| None).collect::<Vec<Option<T>>>());
+        let writers: Vec<Arc<Mutex<TcpStream>>> = self.workers.iter().filter(|worker| worker.alive).map(|worker| worker.writer.clone()).collect();
+        let num_of_running_workers = AtomicUsize::new(writers.len());
+
+        thread::scope(|scope| {
+            if let Some(budget) = budget {
+                let writers = &writers;
+                let num_of_running_workers = &num_of_running_workers;
+
+                scope.spawn(move || {
+                    while num_of_running_workers.load(Ordering::SeqCst) > 0 {
+                        if budget.is_cancelled() {
+                            for writer in writers {
+                                // A broken connection is noticed by the job itself
+                                let _ = send(&mut *writer.lock().unwrap(), &Request::Cancel);
+                            }
+                            break;
+                        }
+
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                });
+            }
+
+            for worker in self.workers.iter_mut().filter(|worker| worker.alive) {
+                let queue = &queue;
+                let results = &results;
+                let job = &job;
+                let num_of_running_workers = &num_of_running_workers;
+
+                scope.spawn(move || {
+                    loop {
+                        if budget.map_or(false, |budget| budget.check().is_some()) {
+                            break;
+                        }
+
+                        let job_id = match queue.lock().unwrap().pop() {
+                            Some(job_id) => job_id,
+                            None => break,
+                        };
+
+                        match job(worker, job_id) {
+                            Ok(result) => results.lock().unwrap()[job_id] = Some(result),
+                            // The job is done locally
+                            Err(JobError::Failed(message)) => warn!("Job {} not done: {}", job_id, message),
+                            Err(JobError::Connection(e)) => {
+                                // Give the job to another worker
+                                worker.mark_dead(&e);
+                                queue.lock().unwrap().push(job_id);
+                                break;
+                            }
+                        }
+                    }
+
+                    num_of_running_workers.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        results.into_inner().unwrap()
+    }
+
+    // One batch for every network, see Driver::train_distributed()
+    pub(crate) fn optimize(&mut self, networks: &mut [Network], indices: &[usize], budget: &TrainingBudget) {
+        let (configuration, training_data) = self.data.clone().expect("WorkerPool::begin() must be called first");
+        let records: Vec<NetworkRecord> = networks.iter().map(job_record).collect();
+
+        let results = self.run_jobs(networks.len(), Some(budget), |worker, job_id| {
+            let request = Request::Optimize {
+                job_id,
+                record: Box::new(records[job_id].clone()),
+                indices: indices.to_vec(),
+                max_seconds: budget.remaining_seconds(),
+                max_evaluations: budget.remaining_evaluations(),
+            };
+
+            match worker.request(&request)? {
+                Response::Optimized { job_id: received_id, record, num_of_evaluations } if received_id == job_id => Ok((record, num_of_evaluations)),
+                response => Err(format_err!("Unexpected response: {:?}", response).into()),
+            }
+        });
+
+        let mut local_networks = Vec::new();
+
+        for (network, result) in networks.iter_mut().zip(results) {
+            match result {
+                Some((record, num_of_evaluations)) => {
+                    network.update_from_record(*record);
+                    budget.add_evaluations(num_of_evaluations);
+                }
+                None => local_networks.push(network),
+            }
+        }
+
+        let objective = BatchObjective { training_data: &training_data, indices };
+        local_networks.par_iter_mut().for_each(|network| {
+            optimize_network(network, &objective, budget, configuration.num_of_iterations);
+        });
+    }
+
+    // Batch error of each property
+    pub fn evaluate(&mut self, properties: &[Property], indices: &[usize]) -> Vec<f64> {
+        let (configuration, training_data) = self.data.clone().expect("WorkerPool::begin() must be called first");
+
+        let results = self.run_jobs(properties.len(), None, |worker, job_id| {
+            let request = Request::Evaluate {
+                job_id,
+                property: properties[job_id].clone(),
+                indices: indices.to_vec(),
+            };
+
+            match worker.request(&request)? {
+                Response::Evaluated { job_id: received_id, error } if received_id == job_id => Ok(error),
+                response => Err(format_err!("Unexpected response: {:?}", response).into()),
+            }
+        });
+
+        results.into_par_iter().enumerate().map(|(job_id, result)| {
+            result.unwrap_or_else(|| evaluate_property(&configuration, &training_data, properties[job_id].clone(), indices))
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use toml;
+
+    use driver::{Driver};
+    use nes::{WeightOptimizer};
+    use stopping::{StopReason};
+
+    fn configuration() -> DriverConfiguration {
+        toml::from_str("
+            num_of_input_nodes = 2
+            num_of_output_nodes = 1
+            num_of_networks = 4
+            num_of_node_mutation = 10
+            num_of_iterations = 10
+            num_of_batch_iterations = 3
+            batch_size = 4
+            use_trained_networks = false
+            node_threshold = 0.5
+            stop_at_desired_error = false
+        ").unwrap()
+    }
+
+    fn xor_data() -> TrainingData {
+        TrainingData {
+            provided_input: vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]],
+            expected_output: vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]],
+        }
+    }
+
+    fn start_worker() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener));
+        address
+    }
+
+    // Accepts connections and closes them at once
+    fn start_broken_worker() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                drop(stream);
+            }
+        });
+        address
+    }
+
+    #[test]
+    fn train_with_workers() {
+        let addresses = [start_worker(), start_worker()];
+        let addresses: Vec<&str> = addresses.iter().map(|address| address.as_str()).collect();
+        let mut pool = WorkerPool::connect(&addresses, Duration::from_secs(60));
+        assert_eq!(pool.num_of_workers(), 2);
+
+        let mut driver = Driver::new_from_config(configuration()).unwrap();
+        let history = driver.train_distributed(&xor_data(), &mut pool).unwrap();
+
+        assert_eq!(history.stop_reason, Some(StopReason::Completed));
+        assert_eq!(history.iterations.len(), 3);
+        assert!(history.num_of_evaluations > 3 * 4);
+        assert_eq!(pool.num_of_workers(), 2);
+    }
+
+    #[test]
+    fn broken_worker_does_not_stop_training() {
+        let addresses = [start_broken_worker(), start_worker(), "127.0.0.1:1".to_string()];
+        let addresses: Vec<&str> = addresses.iter().map(|address| address.as_str()).collect();
+        let mut pool = WorkerPool::connect(&addresses, Duration::from_secs(60));
+
+        let mut driver = Driver::new_from_config(configuration()).unwrap();
+        let history = driver.train_distributed(&xor_data(), &mut pool).unwrap();
+
+        assert_eq!(history.iterations.len(), 3);
+        assert_eq!(pool.num_of_workers(), 1);
+
+        let property: Property = toml::from_str(include_str!("trained_networks/xor02.toml")).unwrap();
+        let errors = pool.evaluate(&[property.clone(), property], &[0, 1, 2, 3]);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0], errors[1]);
+    }
+
+    #[test]
+    fn failed_job_keeps_the_worker() {
+        let addresses = [start_worker()];
+        let addresses: Vec<&str> = addresses.iter().map(|address| address.as_str()).collect();
+        let mut pool = WorkerPool::connect(&addresses, Duration::from_secs(60));
+        // Without begin() the worker has no training data and fails every job
+        pool.data = Some((configuration(), xor_data()));
+
+        let property: Property = toml::from_str(include_str!("trained_networks/xor02.toml")).unwrap();
+        let errors = pool.evaluate(::std::slice::from_ref(&property), &[0, 1, 2, 3]);
+
+        assert_eq!(errors, vec![evaluate_property(&configuration(), &xor_data(), property, &[0, 1, 2, 3])]);
+        assert_eq!(pool.num_of_workers(), 1);
+    }
+
+    #[test]
+    fn jobs_keep_the_budget() {
+        let mut configuration = configuration();
+        configuration.num_of_iterations = 1000;
+        let mut data = None;
+        let token = CancellationToken::new();
+        let set_data = Request::SetData { configuration: Box::new(configuration.clone()), training_data: xor_data() };
+        assert_eq!(handle_request(set_data, &mut data, &token).unwrap(), Response::Ready);
+
+        let request = Request::Optimize {
+            job_id: 0,
+            record: Box::new(Network::new(configuration).to_record()),
+            indices: vec![0, 1, 2, 3],
+            max_seconds: None,
+            max_evaluations: Some(1),
+        };
+        match handle_request(request, &mut data, &token).unwrap() {
+            Response::Optimized { num_of_evaluations, .. } => assert_eq!(num_of_evaluations, 1),
+            response => panic!("Unexpected response: {:?}", response),
+        }
+    }
+
+    #[test]
+    fn cancel_stops_the_running_job() {
+        let mut configuration = configuration();
+        configuration.num_of_iterations = 1_000_000_000;
+        let stream = TcpStream::connect(start_worker()).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        send(&mut writer, &Request::SetData { configuration: Box::new(configuration.clone()), training_data: xor_data() }).unwrap();
+        assert_eq!(receive::<_, Response>(&mut reader).unwrap(), Response::Ready);
+
+        let request = Request::Optimize {
+            job_id: 3,
+            record: Box::new(Network::new(configuration).to_record()),
+            indices: vec![0, 1, 2, 3],
+            max_seconds: None,
+            max_evaluations: None,
+        };
+        send(&mut writer, &request).unwrap();
+        send(&mut writer, &Request::Cancel).unwrap();
+
+        match receive(&mut reader).unwrap() {
+            Response::Optimized { job_id, .. } => assert_eq!(job_id, 3),
+            response => panic!("Unexpected response: {:?}", response),
+        }
+    }
+
+    #[test]
+    fn workers_continue_the_adaptation() {
+        let mut configuration = configuration();
+        configuration.adaptive_operator_selection = true;
+        configuration.step_size_adaptation = true;
+        configuration.step_size_window = 5;
+        let mut network = Network::new(configuration.clone());
+        network.lineage_mut().add_error(0, 1.0);
+
+        let budget = TrainingBudget::new(None, None, CancellationToken::new());
+        let (result, _) = optimize_record(&configuration, &xor_data(), job_record(&network), &[0, 1, 2, 3], &budget);
+        let state = result.state.mutation_state.clone().unwrap();
+        assert_ne!(state, network.mutation_settings().state());
+
+        network.update_from_record(result);
+        assert_eq!(network.mutation_settings().state(), state);
+        assert_eq!(network.lineage().error_history.len(), 1);
+        assert!(network.lineage().mutations_applied > 0);
+        assert_eq!(network.lineage().operator_counts.values().sum::<u64>(), network.lineage().mutations_applied);
+
+        // The step sizes of the evolution strategy come back, too
+        configuration.weight_optimizer = WeightOptimizer::SeparableNes;
+        let mut network = Network::new(configuration.clone());
+        let (result, _) = optimize_record(&configuration, &xor_data(), network.to_record(), &[0, 1, 2, 3], &budget);
+        let sigma = result.state.nes_sigma.clone().unwrap();
+
+        network.update_from_record(result);
+        assert_eq!(network.weight_optimizer(), WeightOptimizer::SeparableNes);
+        assert_eq!(network.to_record().state.nes_sigma, Some(sigma));
+    }
+}
@@ -1,18 +1,41 @@
 use std::fs;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{Write, Read, BufWriter, BufReader};
 use std::f64;
-use std::time::Instant;
+use std::cmp::Ordering;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use rand::{self, Rng};
+use rand::{self, Rng, XorShiftRng, FromEntropy};
 use failure::Error;
 use rayon::prelude::*;
 use toml;
 
 use network::{Network};
-use network_configurations;
+use property::{Property};
+use adaptation::{ShapeMapping};
+use mutation::{ConfigurationError, MutationSettings, OperatorStatistics};
+use analysis::{GraphAnalysis};
+use validation::{InvalidNetwork};
+use repair::{LoadMode, RepairReport};
+use population::{PopulationArchive, SnapshotRotation};
+use registry::{NetworkRegistry};
+use lineage::{self, Lineage, Origin};
+use history::{self, TrainingHistory, IterationRecord};
+use stopping::{CancellationToken, StopReason, TrainingBudget};
+use fitness::{Evaluator, Fitness, FitnessObjective, Objective};
+use selection::{self, Candidate, SelectionMethod, SelectionSettings, SelectionStrategy};
+use distributed::{WorkerPool};
+use acceptance::{AcceptancePolicy, AcceptanceState};
+use gradient::{self, GradientOptimizer};
+use nes::{self, WeightOptimizer};
+use novelty::{self, NoveltyArchive};
+use stagnation::{self, StagnationAction, StagnationMonitor, StagnationRule};
+use hyperparameters::{self, Hyperparameters};
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DriverConfiguration {
     #[serde(default="DriverConfiguration::num_of_input_nodes")]
     pub num_of_input_nodes: usize,
@@ -36,16 +59,165 @@ pub struct DriverConfiguration {
     pub num_of_cycles: usize,
     #[serde(default="DriverConfiguration::use_trained_networks")]
     pub use_trained_networks: bool,
+    #[serde(default="DriverConfiguration::max_seed_networks")]
+    pub max_seed_networks: usize,
+    #[serde(default="DriverConfiguration::trained_networks_directories")]
+    pub trained_networks_directories: Vec<String>,
+    #[serde(default="DriverConfiguration::freeze_loaded_networks")]
+    pub freeze_loaded_networks: bool,
+    #[serde(default="DriverConfiguration::frozen_node_ranges")]
+    pub frozen_node_ranges: Vec<(usize, usize)>,
     #[serde(default="DriverConfiguration::node_threshold")]
     pub node_threshold: f64,
-    #[serde(default="DriverConfiguration::desired_error")]
-    pub clone_threshold: f64,
     #[serde(default="DriverConfiguration::clone_threshold")]
+    pub clone_threshold: f64,
+    #[serde(default="DriverConfiguration::desired_error")]
     pub desired_error: f64,
     #[serde(default="DriverConfiguration::num_of_threads")]
     pub num_of_threads: usize,
     #[serde(default="DriverConfiguration::batch_output")]
     pub batch_output: bool,
+    // Relative probability for each mutation operation, missing ones have weight 1.0
+    #[serde(default="DriverConfiguration::node_operation_weights")]
+    pub node_operation_weights: BTreeMap<String, f64>,
+    #[serde(default="DriverConfiguration::property_operation_weights")]
+    pub property_operation_weights: BTreeMap<String, f64>,
+    #[serde(default="DriverConfiguration::delta_limits")]
+    pub delta_limits: [f64; 4],
+    #[serde(default="DriverConfiguration::weight_range")]
+    pub weight_range: f64,
+    #[serde(default="DriverConfiguration::bias_range")]
+    pub bias_range: f64,
+    // Shift the operator probabilities towards operations that produce improvements
+    #[serde(default="DriverConfiguration::adaptive_operator_selection")]
+    pub adaptive_operator_selection: bool,
+    #[serde(default="DriverConfiguration::operator_adaptation_rate")]
+    pub operator_adaptation_rate: f64,
+    #[serde(default="DriverConfiguration::min_operator_probability")]
+    pub min_operator_probability: f64,
+    // Scale the delta limits with the 1/5th success rule
+    #[serde(default="DriverConfiguration::step_size_adaptation")]
+    pub step_size_adaptation: bool,
+    #[serde(default="DriverConfiguration::step_size_window")]
+    pub step_size_window: usize,
+    // Remove nodes that have no influence on the outputs after each batch iteration
+    #[serde(default="DriverConfiguration::prune_dead_nodes")]
+    pub prune_dead_nodes: bool,
+    // Check every network after each mutation, only in debug builds
+    #[serde(default="DriverConfiguration::validate_mutations")]
+    pub validate_mutations: bool,
+    // "strict" or "repair", used by load_network
+    #[serde(default="DriverConfiguration::load_mode")]
+    pub load_mode: LoadMode,
+    // Save the whole population after each batch iteration into this directory
+    #[serde(default="DriverConfiguration::population_snapshot_directory")]
+    pub population_snapshot_directory: Option<String>,
+    // Only keep the newest snapshots, 0: keep all
+    #[serde(default="DriverConfiguration::num_of_population_snapshots")]
+    pub num_of_population_snapshots: usize,
+    // Stop training after this many seconds (wall clock)
+    #[serde(default="DriverConfiguration::max_training_seconds")]
+    pub max_training_seconds: Option<f64>,
+    // Stop training after this many batch error calculations
+    #[serde(default="DriverConfiguration::max_fitness_evaluations")]
+    pub max_fitness_evaluations: Option<usize>,
+    // Stop training when the best network reaches desired_error on the full
+    // training set (or the validation set if one is given)
+    #[serde(default="DriverConfiguration::stop_at_desired_error")]
+    pub stop_at_desired_error: bool,
+    // "truncation", "tournament", "fitness_proportional", "rank", "plus" or "comma"
+    #[serde(default="DriverConfiguration::selection")]
+    pub selection: SelectionMethod,
+    #[serde(default="DriverConfiguration::tournament_size")]
+    pub tournament_size: usize,
+    // Number of best networks that always survive
+    #[serde(default="DriverConfiguration::elitism")]
+    pub elitism: usize,
+    // Number of copies made after each batch iteration.
+    // 0: truncation only clones the best network if it beats the second by clone_threshold
+    #[serde(default="DriverConfiguration::num_of_offspring")]
+    pub num_of_offspring: usize,
+    // "hill_climbing", "simulated_annealing", "threshold_accepting" or "late_acceptance":
+    // decides if a mutation that makes a network worse is kept during Network::optimize()
+    #[serde(default="DriverConfiguration::acceptance")]
+    pub acceptance: AcceptancePolicy,
+    // Start temperature for annealing, start threshold for threshold accepting.
+    // Both compare the relative worsening (new_error - current_error) / current_error.
+    #[serde(default="DriverConfiguration::initial_temperature")]
+    pub initial_temperature: f64,
+    // The temperature is multiplied by this after each call of Network::optimize(),
+    // that is num_of_iterations times per batch iteration
+    #[serde(default="DriverConfiguration::cooling_rate")]
+    pub cooling_rate: f64,
+    #[serde(default="DriverConfiguration::min_temperature")]
+    pub min_temperature: f64,
+    // Number of mutations a network error is compared with later, should be smaller than num_of_node_mutation
+    #[serde(default="DriverConfiguration::late_acceptance_length")]
+    pub late_acceptance_length: usize,
+    // "sgd" or "adam", used by Driver::fine_tune() and fine_tuning_interval
+    #[serde(default="DriverConfiguration::gradient_optimizer")]
+    pub gradient_optimizer: GradientOptimizer,
+    #[serde(default="DriverConfiguration::learning_rate")]
+    pub learning_rate: f64,
+    // Number of gradient steps for each fine-tuning
+    #[serde(default="DriverConfiguration::num_of_gradient_steps")]
+    pub num_of_gradient_steps: usize,
+    // Fine-tune the weights of the best network on the current batch after every n batch iterations,
+    // only for training data. 0: never during training
+    #[serde(default="DriverConfiguration::fine_tuning_interval")]
+    pub fine_tuning_interval: usize,
+    // "mutation" or "separable_nes": how Network::optimize() changes a network.
+    // The evolution strategy only tunes weights and biases, the structure stays fixed.
+    #[serde(default="DriverConfiguration::weight_optimizer")]
+    pub weight_optimizer: WeightOptimizer,
+    // Probability that a new network uses weight_optimizer, the others use mutation.
+    // Copies keep the optimizer of their parent.
+    #[serde(default="DriverConfiguration::weight_optimizer_share")]
+    pub weight_optimizer_share: f64,
+    // Start step size of the evolution strategy for every weight and bias
+    #[serde(default="DriverConfiguration::initial_sigma")]
+    pub initial_sigma: f64,
+    // Novelty search: 0.0 selects by error only, 1.0 by novelty only.
+    // Selection uses a weighted sum of the error rank and the novelty rank.
+    #[serde(default="DriverConfiguration::novelty_weight")]
+    pub novelty_weight: f64,
+    // Novelty is the mean distance to the k nearest behaviours in the population and the archive
+    #[serde(default="DriverConfiguration::novelty_k")]
+    pub novelty_k: usize,
+    // The behaviour of a network is its output for these inputs,
+    // empty: all inputs of the training data (must be given for a fitness function)
+    #[serde(default="DriverConfiguration::novelty_probe_inputs")]
+    pub novelty_probe_inputs: Vec<Vec<f64>>,
+    // Number of most novel behaviours added to the archive after each batch iteration
+    #[serde(default="DriverConfiguration::novelty_archive_additions")]
+    pub novelty_archive_additions: usize,
+    // The oldest behaviours are removed first, 0: no limit
+    #[serde(default="DriverConfiguration::max_novelty_archive_size")]
+    pub max_novelty_archive_size: usize,
+    // Stagnation rules, 0: off. No improvement of the best error (full_error if it is calculated)
+    // for this many batch iterations
+    #[serde(default="DriverConfiguration::stagnation_iterations")]
+    pub stagnation_iterations: usize,
+    // The best network has been in first place this many times (first_place_counter),
+    // after the rule has fired it counts again from there
+    #[serde(default="DriverConfiguration::max_first_place_iterations")]
+    pub max_first_place_iterations: u64,
+    // What happens when a rule fires:
+    // "re_randomize_bottom_half", "inject_new_networks" and / or "boost_mutation"
+    #[serde(default="DriverConfiguration::stagnation_actions")]
+    pub stagnation_actions: Vec<StagnationAction>,
+    #[serde(default="DriverConfiguration::num_of_injected_networks")]
+    pub num_of_injected_networks: usize,
+    // Factor for the mutation step sizes
+    #[serde(default="DriverConfiguration::mutation_boost")]
+    pub mutation_boost: f64,
+    // Every network mutates its own node_threshold, num_of_node_mutation and mutation step scale,
+    // the changed values are kept if the network improves
+    #[serde(default="DriverConfiguration::self_adaptive_hyperparameters")]
+    pub self_adaptive_hyperparameters: bool,
+    // Standard deviation of the log-normal hyperparameter mutation
+    #[serde(default="DriverConfiguration::hyperparameter_learning_rate")]
+    pub hyperparameter_learning_rate: f64,
 }
 
 impl DriverConfiguration {
@@ -60,63 +232,311 @@ impl DriverConfiguration {
     fn batch_size() -> usize {10}
     fn num_of_cycles() -> usize {2}
     fn use_trained_networks() -> bool {true}
+    fn max_seed_networks() -> usize {3}
+    fn trained_networks_directories() -> Vec<String> {Vec::new()}
+    fn freeze_loaded_networks() -> bool {false}
+    fn frozen_node_ranges() -> Vec<(usize, usize)> {Vec::new()}
     fn node_threshold() -> f64 {0.1}
     fn clone_threshold() -> f64 {0.9}
     fn desired_error() -> f64 {0.01}
     fn num_of_threads() -> usize {1}
     fn batch_output() -> bool {false}
+    fn node_operation_weights() -> BTreeMap<String, f64> {BTreeMap::new()}
+    fn property_operation_weights() -> BTreeMap<String, f64> {BTreeMap::new()}
+    fn delta_limits() -> [f64; 4] {[1.0, 0.001, 0.00001, 0.0000001]}
+    fn weight_range() -> f64 {10.0}
+    fn bias_range() -> f64 {10.0}
+    fn adaptive_operator_selection() -> bool {false}
+    fn operator_adaptation_rate() -> f64 {0.1}
+    fn min_operator_probability() -> f64 {0.01}
+    fn step_size_adaptation() -> bool {false}
+    fn step_size_window() -> usize {100}
+    fn prune_dead_nodes() -> bool {false}
+    fn validate_mutations() -> bool {false}
+    fn load_mode() -> LoadMode {LoadMode::Repair}
+    fn population_snapshot_directory() -> Option<String> {None}
+    fn num_of_population_snapshots() -> usize {10}
+    fn max_training_seconds() -> Option<f64> {None}
+    fn max_fitness_evaluations() -> Option<usize> {None}
+    fn stop_at_desired_error() -> bool {true}
+    fn selection() -> SelectionMethod {SelectionMethod::Truncation}
+    fn tournament_size() -> usize {3}
+    fn elitism() -> usize {1}
+    fn num_of_offspring() -> usize {0}
+    fn acceptance() -> AcceptancePolicy {AcceptancePolicy::HillClimbing}
+    fn initial_temperature() -> f64 {0.1}
+    fn cooling_rate() -> f64 {0.9999}
+    fn min_temperature() -> f64 {0.0}
+    fn late_acceptance_length() -> usize {50}
+    fn gradient_optimizer() -> GradientOptimizer {GradientOptimizer::Adam}
+    fn learning_rate() -> f64 {0.01}
+    fn num_of_gradient_steps() -> usize {100}
+    fn fine_tuning_interval() -> usize {0}
+    fn weight_optimizer() -> WeightOptimizer {WeightOptimizer::Mutation}
+    fn weight_optimizer_share() -> f64 {1.0}
+    fn initial_sigma() -> f64 {0.1}
+    fn novelty_weight() -> f64 {0.0}
+    fn novelty_k() -> usize {10}
+    fn novelty_probe_inputs() -> Vec<Vec<f64>> {Vec::new()}
+    fn novelty_archive_additions() -> usize {1}
+    fn max_novelty_archive_size() -> usize {1000}
+    fn stagnation_iterations() -> usize {0}
+    fn max_first_place_iterations() -> u64 {0}
+    fn stagnation_actions() -> Vec<StagnationAction> {vec![StagnationAction::ReRandomizeBottomHalf]}
+    fn num_of_injected_networks() -> usize {2}
+    fn mutation_boost() -> f64 {10.0}
+    fn self_adaptive_hyperparameters() -> bool {false}
+    fn hyperparameter_learning_rate() -> f64 {0.2}
+
+    pub fn validate(&self) -> Result<(), Error> {
+        self.validate_sizes()?;
+        MutationSettings::validate(self)?;
+        AcceptanceState::validate(self)?;
+        gradient::validate(self)?;
+        nes::validate(self)?;
+        novelty::validate(self)?;
+        stagnation::validate(self)?;
+        hyperparameters::validate(self)?;
+        Ok(())
+    }
+
+    fn validate_sizes(&self) -> Result<(), ConfigurationError> {
+        let mut errors = Vec::new();
+
+        if self.num_of_input_nodes == 0 {
+            errors.push("num_of_input_nodes must be > 0".to_string());
+        }
+
+        if self.num_of_output_nodes == 0 {
+            errors.push("num_of_output_nodes must be > 0".to_string());
+        }
+
+        if self.initial_network_size == 0 {
+            errors.push("initial_network_size must be > 0".to_string());
+        }
+
+        if self.max_network_size < self.initial_network_size {
+            errors.push(format!("max_network_size must be >= initial_network_size ({}), given: {}", self.initial_network_size, self.max_network_size));
+        }
+
+        if self.num_of_networks < 2 {
+            errors.push(format!("num_of_networks must be > 1, given: {}", self.num_of_networks));
+        }
+
+        if self.num_of_node_mutation == 0 {
+            errors.push("num_of_node_mutation must be > 0".to_string());
+        }
+
+        if self.batch_size == 0 {
+            errors.push("batch_size must be > 0".to_string());
+        }
+
+        if self.num_of_cycles == 0 {
+            errors.push("num_of_cycles must be > 0".to_string());
+        }
+
+        if !(self.node_threshold > 0.0 && self.node_threshold < 1.0) {
+            errors.push(format!("node_threshold must be in (0, 1), given: {}", self.node_threshold));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigurationError { errors })
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Driver {
     configuration: DriverConfiguration,
     networks: Vec<Network>,
+    // Counts over all calls of train()
+    batch_iteration: usize,
+    // Lineage of every network that was part of the population, including removed ones
+    lineage_archive: BTreeMap<String, Lineage>,
+    cancellation_token: CancellationToken,
+    selection_strategy: Arc<dyn SelectionStrategy>,
+    // Behaviours seen so far, only used for novelty search
+    novelty_archive: NoveltyArchive,
+    stagnation_monitor: StagnationMonitor,
+    // Batch of the last batch iteration, migrants from other islands are evaluated on it
+    batch_indices: Vec<usize>,
+}
+
+struct MeanHyperparameters {
+    node_threshold: f64,
+    num_of_node_mutation: f64,
+    step_scale: f64,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct LineageExport {
+    networks: Vec<Lineage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TrainingData {
     pub provided_input: Vec<Vec<f64>>,
     pub expected_output: Vec<Vec<f64>>,
 }
 
+// Sort order for errors, NaN is worse than everything else
+pub fn compare_errors(error1: f64, error2: f64) -> Ordering {
+    match (error1.is_nan(), error2.is_nan()) {
+        (false, false) => error1.partial_cmp(&error2).unwrap(),
+        (false, true) => Ordering::Less,
+        (true, false) => Ordering::Greater,
+        (true, true) => Ordering::Equal,
+    }
+}
+
+// Labelled samples, the error of a network is calculated on a (shuffled) batch
+pub(crate) struct DataObjective<'a> {
+    training_data: &'a TrainingData,
+    validation_data: Option<&'a TrainingData>,
+    indices: Vec<usize>,
+    change_batch: bool,
+    rng: XorShiftRng,
+}
+
+impl<'a> Objective for DataObjective<'a> {
+    fn next_batch(&mut self) {
+        if self.change_batch {
+            self.rng.shuffle(&mut self.indices);
+        }
+    }
+
+    fn batch_indices(&self) -> Option<&[usize]> {
+        Some(&self.indices)
+    }
+
+    fn probe_inputs(&self) -> Option<&[Vec<f64>]> {
+        Some(&self.training_data.provided_input)
+    }
+
+    fn error(&self, network: &mut Network) -> f64 {
+        network.calculate_batch_and_error(&self.indices, self.training_data)
+    }
+
+    fn fine_tune(&self, network: &mut Network) -> Option<(f64, f64)> {
+        let batch_size = self.training_data.provided_input.len().min(network.configuration().batch_size);
+        let result = network.fine_tune(&self.indices[..batch_size], self.training_data);
+        network.reset_best_error(self);
+        Some(result)
+    }
+
+    fn full_error(&self, network: &mut Network) -> f64 {
+        match self.validation_data {
+            Some(validation_data) => network.calculate_total_error(validation_data),
+            // The batch is the whole training set
+            None if !self.change_batch => network.best_error,
+            None => network.calculate_total_error(self.training_data),
+        }
+    }
+}
+
+// Inner loop for one network and one batch
+pub(crate) fn optimize_network<O: Objective>(network: &mut Network, objective: &O, budget: &TrainingBudget, num_of_iterations: usize) {
+    // Reset best error for this batch
+    network.reset_best_error(objective);
+    budget.add_evaluations(1);
+    for j in 0..num_of_iterations {
+        if budget.check().is_some() {
+            // Safe point: the network holds its best property for this batch
+            break;
+        }
+
+        budget.add_evaluations(network.optimize(objective));
+
+        if network.is_good_enough() {
+            // No more training needed for this network
+            info!("Good enough after {} iterations", j);
+            break;
+        }
+    }
+}
+
+fn seconds(duration: Duration) -> f64 {
+    (duration.as_secs() as f64) + ((duration.subsec_nanos() as f64) * 1e-9)
+}
+
+fn check_network(network: &Network) -> Result<(), Error> {
+    let violations = network.validate();
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(InvalidNetwork { id: network.id.clone(), violations }.into())
+    }
+}
+
+fn prepare_network(network: &mut Network, mode: LoadMode, num_of_loaded_nodes: usize) -> Result<RepairReport, Error> {
+    let report = match mode {
+        LoadMode::Strict => RepairReport::default(),
+        LoadMode::Repair => network.fix(),
+    };
+
+    for change in &report.changes {
+        warn!("Network {}: {}", network.id, change);
+    }
+
+    check_network(network)?;
+    network.freeze_loaded_nodes(num_of_loaded_nodes);
+
+    Ok(report)
+}
+
 impl Driver {
-    pub fn new_from_config(configuration: DriverConfiguration) -> Driver {
-        assert!(configuration.num_of_input_nodes > 0);
-        assert!(configuration.num_of_output_nodes > 0);
-        assert!(configuration.initial_network_size > 0);
-        assert!(configuration.max_network_size >= configuration.initial_network_size);
-        assert!(configuration.num_of_networks > 1);
-        assert!(configuration.num_of_node_mutation > 0);
-        assert!(configuration.batch_size > 0);
-        assert!(configuration.num_of_cycles > 0);
-        assert!(configuration.node_threshold > 0.0 && configuration.node_threshold < 1.0);
+    pub fn new_from_config(configuration: DriverConfiguration) -> Result<Driver, Error> {
+        configuration.validate()?;
+
+        let mut registry = NetworkRegistry::bundled();
+
+        for directory in &configuration.trained_networks_directories {
+            if let Err(e) = registry.register_directory(directory) {
+                error!("Could not register trained networks from {}: {}", directory, e);
+            }
+        }
+
+        Self::new_with_registry(configuration, &registry)
+    }
+
+    pub fn new_with_registry(configuration: DriverConfiguration, registry: &NetworkRegistry) -> Result<Driver, Error> {
+        configuration.validate()?;
 
         let mut networks = Vec::with_capacity(configuration.num_of_networks);
 
         if configuration.use_trained_networks {
-            /*
-            networks.push(network_configurations::xor02(configuration.clone()));
-            networks.push(network_configurations::xor05(configuration.clone()));
-            */
-            networks.push(network_configurations::iris03(configuration.clone()));
-            networks.push(network_configurations::adder04(configuration.clone()));
-            networks.push(network_configurations::adder06(configuration.clone()));
-        } else {
-            for _ in 0..configuration.num_of_networks {
-                networks.push(Network::new(configuration.clone()));
-            }
+            networks.extend(registry.seed_networks(&configuration));
+        }
+
+        while networks.len() < configuration.num_of_networks {
+            networks.push(Network::new(configuration.clone()));
         }
 
-        Driver {
+        let selection_strategy = Arc::from(selection::new_strategy(configuration.selection, configuration.tournament_size));
+
+        let mut driver = Driver {
             configuration,
             networks,
-        }
+            batch_iteration: 0,
+            lineage_archive: BTreeMap::new(),
+            cancellation_token: CancellationToken::new(),
+            selection_strategy,
+            novelty_archive: NoveltyArchive::default(),
+            stagnation_monitor: StagnationMonitor::default(),
+            batch_indices: Vec::new(),
+        };
+
+        driver.archive_lineage();
+        Ok(driver)
     }
 
     pub fn new_from_json(data: &str) -> Result<Driver, Error> {
-        let configuration: DriverConfiguration = toml::from_str(&data)?;
+        let configuration: DriverConfiguration = toml::from_str(data)?;
 
-        Ok(Self::new_from_config(configuration))
+        Self::new_from_config(configuration)
     }
 
     pub fn new_from_file(filename: &str) -> Result<Driver, Error> {
@@ -128,7 +548,7 @@ impl Driver {
         Self::new_from_json(&data)
     }
 
-    pub fn train_from_file(&mut self, filename: &str) -> Result<(), Error> {
+    pub fn train_from_file(&mut self, filename: &str) -> Result<TrainingHistory, Error> {
         info!("Load training data from file: {}", filename);
 
         let mut data = String::new();
@@ -143,10 +563,12 @@ impl Driver {
         self.train(&training_data)
     }
 
-    pub fn train(&mut self, training_data: &TrainingData) -> Result<(), Error> {
-        let start_time = Instant::now();
-        info!("Begin training");
+    pub fn train(&mut self, training_data: &TrainingData) -> Result<TrainingHistory, Error> {
+        self.train_with_validation(training_data, None)
+    }
 
+    // The validation data is only used for the desired error stopping criterion
+    pub fn train_with_validation(&mut self, training_data: &TrainingData, validation_data: Option<&TrainingData>) -> Result<TrainingHistory, Error> {
         let input_len = training_data.provided_input.len();
         let output_len = training_data.expected_output.len();
 
@@ -155,71 +577,284 @@ impl Driver {
 
         info!("Number of entries: {}", input_len);
 
-        let change_batch =  self.configuration.batch_size != input_len;
+        let mut objective = DataObjective {
+            training_data,
+            validation_data,
+            indices: (0..input_len).collect(),
+            change_batch: self.configuration.batch_size != input_len,
+            rng: XorShiftRng::from_entropy(),
+        };
+
+        self.run_training(&mut objective, None)
+    }
+
+    // Like train(), but the networks are optimized by the workers of the pool.
+    // Jobs of workers that fail are given to other workers or done locally.
+    pub fn train_distributed(&mut self, training_data: &TrainingData, pool: &mut WorkerPool) -> Result<TrainingHistory, Error> {
+        let input_len = training_data.provided_input.len();
+        assert_eq!(input_len, training_data.expected_output.len(), "Error in training data: input and output length do not match");
+        assert!(self.configuration.batch_size <= input_len, "Error in training data: batch size must be <= {} (input length), given: {}", input_len, self.configuration.batch_size);
+
+        pool.begin(&self.configuration, training_data);
+
+        let mut objective = DataObjective {
+            training_data,
+            validation_data: None,
+            indices: (0..input_len).collect(),
+            change_batch: self.configuration.batch_size != input_len,
+            rng: XorShiftRng::from_entropy(),
+        };
+
+        self.run_training(&mut objective, Some(pool))
+    }
+
+    // For tasks without labelled data, for example controllers scored by a simulation
+    pub fn train_with_fitness<F: Fitness>(&mut self, fitness: &F) -> Result<TrainingHistory, Error> {
+        let mut objective = FitnessObjective {
+            fitness,
+        };
+
+        self.run_training(&mut objective, None)
+    }
+
+    fn run_training<O: Objective>(&mut self, objective: &mut O, mut pool: Option<&mut WorkerPool>) -> Result<TrainingHistory, Error> {
+        let start_time = Instant::now();
+        let mut history = TrainingHistory::default();
+        info!("Begin training");
 
         let mut rng = rand::thread_rng();
 
         let num_of_iterations = self.configuration.num_of_iterations;
-        let mut indices : Vec<usize> = (0..input_len).collect();
+
+        let budget = TrainingBudget::new(self.configuration.max_training_seconds, self.configuration.max_fitness_evaluations, self.cancellation_token.clone());
+        let mut stop_reason = StopReason::Completed;
+
+        let use_novelty = self.configuration.novelty_weight > 0.0;
+        let probe_inputs = if !self.configuration.novelty_probe_inputs.is_empty() {
+            self.configuration.novelty_probe_inputs.clone()
+        } else {
+            objective.probe_inputs().map(|inputs| inputs.to_vec()).unwrap_or_default()
+        };
+
+        if use_novelty && probe_inputs.is_empty() {
+            bail!("Novelty search needs novelty_probe_inputs if there is no training data");
+        }
 
         for i in 0..self.configuration.num_of_batch_iterations {
-            if change_batch {
-                rng.shuffle(&mut indices);
+            if let Some(reason) = budget.check() {
+                stop_reason = reason;
+                break;
             }
 
-            self.networks.par_iter_mut().for_each(|network| {
-                // Reset best error for this batch
-                network.reset_best_error(&indices, &training_data);
-                for j in 0..num_of_iterations {
-                    network.optimize_batch(&indices, &training_data);
+            let batch_iteration = self.batch_iteration;
 
-                    if network.is_good_enough() {
-                        // No more training needed for this network
-                        info!("Good enough after {} iterations", j);
-                        break;
+            objective.next_batch();
+            if let Some(indices) = objective.batch_indices() {
+                self.batch_indices = indices.to_vec();
+            }
+            let objective = &*objective;
+
+            match (pool.as_mut(), objective.batch_indices()) {
+                (Some(pool), Some(indices)) => pool.optimize(&mut self.networks, indices, &budget),
+                _ => self.networks.par_iter_mut().for_each(|network| {
+                    optimize_network(network, objective, &budget, num_of_iterations);
+                }),
+            }
+
+            if self.configuration.prune_dead_nodes {
+                for network in &mut self.networks {
+                    let num_of_removed_nodes = network.prune_dead_nodes();
+                    if num_of_removed_nodes > 0 {
+                        debug!("Removed {} dead nodes from network {}", num_of_removed_nodes, network.id);
+                        network.lineage_mut().add_event(batch_iteration, format!("removed {} dead nodes", num_of_removed_nodes));
                     }
                 }
-            });
+            }
+
+            let fine_tuning_interval = self.configuration.fine_tuning_interval;
+            if fine_tuning_interval > 0 && (batch_iteration + 1) % fine_tuning_interval == 0 {
+                if let Some(best) = self.networks.iter_mut().min_by(|n1, n2| compare_errors(n1.best_error, n2.best_error)) {
+                    if let Some((start_error, error)) = objective.fine_tune(best) {
+                        debug!("Fine-tuned network {}, batch error: {} -> {}", best.id, start_error, error);
+                        best.lineage_mut().add_event(batch_iteration, format!("fine-tuned: {} -> {}", start_error, error));
+                    }
+                }
+            }
+
+            for network in &mut self.networks {
+                let best_error = network.best_error;
+                network.lineage_mut().add_error(batch_iteration, best_error);
+            }
+
+            // Keep the lineage of networks that are removed now
+            self.archive_lineage();
+
+            self.networks.sort_unstable_by(|n1, n2| compare_errors(n1.best_error, n2.best_error));
+
+            if self.networks[0].best_error < 0.0 {
+                bail!("Errors must be >= 0, network {} has: {}", self.networks[0].id, self.networks[0].best_error);
+            }
+
+            let full_error = if self.configuration.stop_at_desired_error {
+                Some(objective.full_error(&mut self.networks[0]))
+            } else {
+                None
+            };
+
+            let novelty = if use_novelty { Some(self.update_novelty(&probe_inputs)) } else { None };
+            let scores: Vec<f64> = match novelty {
+                Some(ref novelty) => {
+                    let errors: Vec<f64> = self.networks.iter().map(|network| network.best_error).collect();
+                    let scores = novelty::blended_scores(&errors, novelty, self.configuration.novelty_weight);
+
+                    // The selection expects the candidates sorted by their score
+                    let mut scored: Vec<(f64, Network)> = scores.into_iter().zip(self.networks.drain(..)).collect();
+                    scored.sort_by(|(score1, _), (score2, _)| compare_errors(*score1, *score2));
+                    let (scores, networks) = scored.into_iter().unzip();
+                    self.networks = networks;
+                    scores
+                }
+                None => self.networks.iter().map(|network| network.best_error).collect(),
+            };
+
+            let candidates: Vec<Candidate> = self.networks.iter().zip(&scores).map(|(network, score)| Candidate {
+                error: *score,
+                is_offspring: network.lineage().origin == Origin::Clone && network.lineage().created_iteration == batch_iteration,
+            }).collect();
+            let selection_settings = SelectionSettings {
+                num_of_networks: self.configuration.num_of_networks,
+                elitism: self.configuration.elitism,
+                num_of_offspring: self.configuration.num_of_offspring,
+                clone_threshold: self.configuration.clone_threshold,
+            };
+            let selected = self.selection_strategy.select(&candidates, &selection_settings, &mut rng);
+            let (survivors, copies) = selection::split_selection(&selected, candidates.len());
+            assert!(selected.len() > 1, "Selection must return at least two networks");
+
+            // Get rid of the networks that were not selected
+            let mut old_networks: Vec<Option<Network>> = self.networks.drain(..).map(Some).collect();
+            let mut selected_networks: Vec<(usize, Network)> = survivors.iter().map(|index| (*index, old_networks[*index].take().unwrap())).collect();
+            // With novelty search the survivors are ordered by score, but the population stays sorted by error
+            selected_networks.sort_by(|(_, n1), (_, n2)| compare_errors(n1.best_error, n2.best_error));
+            let (survivors, networks): (Vec<usize>, Vec<Network>) = selected_networks.into_iter().unzip();
+            self.networks = networks;
 
-            self.networks.sort_unstable_by(|n1, n2| n1.best_error.partial_cmp(&n2.best_error).unwrap());
-            self.networks.truncate(self.configuration.num_of_networks); // Get rid of worst solutions
             // Give a random network the chance to improve:
-            let index = rng.gen_range::<usize>(1, self.networks.len() - 1);
-            self.networks[index].maybe_add_node();
-
-            // Try to avoid cloning local optimum over and over again
-            if self.networks[0].best_error <= self.configuration.clone_threshold * self.networks[1].best_error {
-                // Clone the best solution:
-                let mut new_network = self.networks[0].clone();
-                new_network.first_place_counter = 0;
-                // And add it to the list of networks:
+            let index = if self.networks.len() > 2 { rng.gen_range::<usize>(1, self.networks.len() - 1) } else { self.networks.len() - 1 };
+            let add_node_target = self.networks[index].id.clone();
+            let node_added = self.networks[index].maybe_add_node();
+            if node_added {
+                self.networks[index].lineage_mut().add_event(batch_iteration, "added node".to_string());
+            }
+
+            let errors: Vec<f64> = self.networks.iter().map(|network| network.best_error).collect();
+            let mut cloned_id = None;
+            let mut clone_id = None;
+
+            for parent_index in &copies {
+                let parent = &self.networks[survivors.iter().position(|index| index == parent_index).unwrap()];
+                let new_network = parent.new_child(Origin::Clone, batch_iteration + 1);
+                debug!("Cloned network {} as {}", parent.id, new_network.id);
+
+                if clone_id.is_none() {
+                    cloned_id = Some(parent.id.clone());
+                    clone_id = Some(new_network.id.clone());
+                }
+
                 self.networks.push(new_network);
             }
 
             self.networks[0].first_place_counter += 1;
 
+            let stagnation_error = full_error.unwrap_or(errors[0]);
+            let stagnation_rule = self.stagnation_monitor.update(stagnation_error, &self.networks[0].id, self.networks[0].first_place_counter, &self.configuration);
+            let stagnation = stagnation_rule.map(|rule| self.handle_stagnation(rule, batch_iteration));
+
+            let num_of_nodes: Vec<usize> = self.networks.iter().map(|network| network.num_of_nodes()).collect();
+            let mean_hyperparameters = self.mean_hyperparameters();
+            if let Some(ref mean) = mean_hyperparameters {
+                debug!("Mean hyperparameters, node threshold: {}, num. of node mutations: {}, step scale: {}", mean.node_threshold, mean.num_of_node_mutation, mean.step_scale);
+            }
+            history.push(IterationRecord {
+                batch_iteration,
+                timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(seconds).unwrap_or(0.0),
+                elapsed_seconds: seconds(start_time.elapsed()),
+                best_error: errors[0],
+                median_error: history::median(&errors),
+                worst_error: errors[errors.len() - 1],
+                full_error,
+                best_id: self.networks[0].id.clone(),
+                min_num_of_nodes: num_of_nodes.iter().cloned().min().unwrap_or(0),
+                max_num_of_nodes: num_of_nodes.iter().cloned().max().unwrap_or(0),
+                mean_num_of_nodes: (num_of_nodes.iter().sum::<usize>() as f64) / (num_of_nodes.len() as f64),
+                population_size: self.networks.len(),
+                add_node_target: Some(add_node_target),
+                node_added,
+                cloned_id,
+                clone_id,
+                num_of_copies: copies.len(),
+                temperature: self.networks[0].acceptance().temperature(),
+                max_novelty: novelty.as_ref().and_then(|novelty| novelty.iter().cloned().max_by(|n1, n2| compare_errors(*n1, *n2))),
+                stagnation,
+                mean_node_threshold: mean_hyperparameters.as_ref().map(|mean| mean.node_threshold),
+                mean_num_of_node_mutation: mean_hyperparameters.as_ref().map(|mean| mean.num_of_node_mutation),
+                mean_step_scale: mean_hyperparameters.as_ref().map(|mean| mean.step_scale),
+            });
+
             info!("Batch iteration: {} of {}", i, self.configuration.num_of_batch_iterations);
             for network in &self.networks {
                 info!("Best error: {}, num. of nodes: {}, id: {}, first place: {}", network.best_error, network.num_of_nodes(), network.id, network.first_place_counter);
             }
+            for (operation, attempts, successes, rate) in self.total_operator_statistics().ranking() {
+                debug!("Operation: {}, attempts: {}, successes: {}, success rate: {}", operation.name(), attempts, successes, rate);
+            }
             info!("-------------------------------------------");
 
             if self.configuration.batch_output {
                 self.save_network("batch_output.toml")?;
             }
 
+            if let Some(ref directory) = self.configuration.population_snapshot_directory {
+                let rotation = SnapshotRotation {
+                    directory: PathBuf::from(directory),
+                    max_num_of_snapshots: self.configuration.num_of_population_snapshots,
+                };
+                rotation.save(&self.population_archive(Some(batch_iteration)), batch_iteration)?;
+            }
+
             // Reseed PRNG with fresh entropy
             for network in &mut self.networks {
                 network.reseed_rng();
             }
+
+            self.batch_iteration += 1;
+
+            if full_error.map_or(false, |error| error <= self.configuration.desired_error) {
+                info!("Desired error reached by network {}", self.networks[0].id);
+                stop_reason = StopReason::DesiredErrorReached;
+                break;
+            }
+        }
+
+        if stop_reason != StopReason::Completed {
+            info!("Training stopped early: {:?}", stop_reason);
+            // The clone of the best network is at the end
+            self.networks.sort_by(|n1, n2| compare_errors(n1.best_error, n2.best_error));
         }
 
-        let duration = start_time.elapsed();
-        let duration = (duration.as_secs() as f64) + ((duration.subsec_nanos() as f64) * 1e-9);
+        history.stop_reason = Some(stop_reason);
+        history.num_of_evaluations = budget.evaluations();
+        self.archive_lineage();
+
+        let duration = seconds(start_time.elapsed());
         info!("End training");
         info!("Time taken: {} seconds", duration);
         info!("Best error: {}, desired error: {}", self.networks[0].best_error, self.configuration.desired_error);
-        Ok(())
+        for (operation, attempts, successes, rate) in self.total_operator_statistics().ranking() {
+            info!("Operation: {}, attempts: {}, successes: {}, success rate: {}", operation.name(), attempts, successes, rate);
+        }
+
+        Ok(history)
     }
 
     pub fn test(&mut self, provided_input: &[f64], expected_output: &[f64]) -> (f64, Vec<f64>) {
@@ -236,21 +871,400 @@ impl Driver {
         self.networks[0].get_output()
     }
 
-    pub fn set_network(&mut self, mut network: Network) {
+    // For example to run an episode with the best network: environment::run_episode(..., &mut driver.evaluator(0), ...)
+    pub fn evaluator(&mut self, index: usize) -> Evaluator<'_> {
+        assert!(index < self.networks.len());
+        Evaluator::new(&mut self.networks[index])
+    }
+
+    pub fn operator_statistics(&self, index: usize) -> &OperatorStatistics {
+        assert!(index < self.networks.len());
+        self.networks[index].operator_statistics()
+    }
+
+    // Statistics of all networks in the current population
+    pub fn total_operator_statistics(&self) -> OperatorStatistics {
+        let mut result = OperatorStatistics::default();
+
+        for network in &self.networks {
+            result.merge(network.operator_statistics());
+        }
+
+        result
+    }
+
+    // Current selection probability of each operation, same order as MutationOperation::all()
+    pub fn operation_probabilities(&self, index: usize) -> Vec<f64> {
+        assert!(index < self.networks.len());
+        self.networks[index].mutation_settings().operation_probabilities()
+    }
+
+    pub fn hyperparameters(&self, index: usize) -> &Hyperparameters {
+        assert!(index < self.networks.len());
+        self.networks[index].hyperparameters()
+    }
+
+    // Population means, None if the hyperparameters are not self-adaptive
+    fn mean_hyperparameters(&self) -> Option<MeanHyperparameters> {
+        if !self.configuration.self_adaptive_hyperparameters || self.networks.is_empty() {
+            return None
+        }
+
+        let num_of_networks = self.networks.len() as f64;
+        let mut result = MeanHyperparameters { node_threshold: 0.0, num_of_node_mutation: 0.0, step_scale: 0.0 };
+
+        for network in &self.networks {
+            let hyperparameters = network.hyperparameters();
+            result.node_threshold += hyperparameters.node_threshold / num_of_networks;
+            result.num_of_node_mutation += (hyperparameters.num_of_node_mutation as f64) / num_of_networks;
+            result.step_scale += network.mutation_settings().step_scale / num_of_networks;
+        }
+
+        Some(result)
+    }
+
+    pub fn analyze(&self, index: usize) -> GraphAnalysis {
+        assert!(index < self.networks.len());
+        self.networks[index].analyze()
+    }
+
+    // Replaces the strategy from the configuration, for example with a custom one
+    pub fn set_selection_strategy(&mut self, selection_strategy: Arc<dyn SelectionStrategy>) {
+        self.selection_strategy = selection_strategy;
+    }
+
+    pub fn set_cancellation_token(&mut self, cancellation_token: CancellationToken) {
+        self.cancellation_token = cancellation_token;
+    }
+
+    pub fn configuration(&self) -> &DriverConfiguration {
+        &self.configuration
+    }
+
+    pub fn num_of_networks(&self) -> usize {
+        self.networks.len()
+    }
+
+    pub fn best_error(&self) -> f64 {
+        self.networks.iter().map(|network| network.best_error).min_by(|e1, e2| compare_errors(*e1, *e2)).unwrap_or(f64::MAX)
+    }
+
+    // Error of the first network on all entries of the data
+    pub fn total_error(&mut self, data: &TrainingData) -> f64 {
+        self.networks[0].calculate_total_error(data)
+    }
+
+    // Post-processing after train(): optimizes the weights of the best network with gradients on all entries,
+    // the structure does not change. Returns the total error before and after.
+    // The best error is measured again on the current batch. If it went up the weights are reverted,
+    // then the total error after is the one before.
+    pub fn fine_tune(&mut self, data: &TrainingData) -> (f64, f64) {
+        let indices: Vec<usize> = (0..data.provided_input.len()).collect();
+        let batch_iteration = self.batch_iteration;
+        let objective = self.batch_objective(data);
+        let best = &mut self.networks[0];
+        let previous_property = best.get_property();
+        let previous_best_error = best.best_error;
+
+        let (start_error, error) = best.fine_tune(&indices, data);
+        best.reset_best_error(&objective);
+
+        if compare_errors(best.best_error, previous_best_error) == Ordering::Greater {
+            info!("Fine-tuning of network {} reverted, batch error: {} -> {}", best.id, previous_best_error, best.best_error);
+            best.set_property(previous_property);
+            best.best_error = previous_best_error;
+            return (start_error, start_error)
+        }
+
+        info!("Fine-tuned network {}, total error: {} -> {}", best.id, start_error, error);
+        best.lineage_mut().add_event(batch_iteration, format!("fine-tuned: {} -> {}", start_error, error));
+
+        (start_error, error)
+    }
+
+    // Copies of the best networks for other islands
+    pub(crate) fn emigrants(&self, num_of_migrants: usize) -> Vec<Network> {
+        let mut networks: Vec<&Network> = self.networks.iter().collect();
+        networks.sort_by(|n1, n2| compare_errors(n1.best_error, n2.best_error));
+
+        networks.iter().take(num_of_migrants).map(|network| network.new_child(Origin::Migrant, self.batch_iteration)).collect()
+    }
+
+    // The current batch of this island, the whole training data if there was no batch iteration yet
+    pub(crate) fn batch_objective<'a>(&self, training_data: &'a TrainingData) -> DataObjective<'a> {
+        let indices = if self.batch_indices.is_empty() {
+            (0..training_data.provided_input.len()).collect()
+        } else {
+            self.batch_indices.clone()
+        };
+
+        DataObjective {
+            training_data,
+            validation_data: None,
+            indices,
+            change_batch: false,
+            rng: XorShiftRng::from_entropy(),
+        }
+    }
+
+    // Migrants replace the worst networks. Migrants that do not fit this island after a repair are dropped.
+    // Their best error was measured on another island, so they are evaluated again with the objective of this island.
+    pub(crate) fn immigrate<O: Objective>(&mut self, migrants: Vec<Network>, objective: &O) {
+        let mut accepted = Vec::with_capacity(migrants.len());
+
+        for mut migrant in migrants {
+            migrant.set_configuration(self.configuration.clone());
+
+            for change in &migrant.fix().changes {
+                debug!("Migrant {}: {}", migrant.id, change);
+            }
+
+            if let Err(e) = check_network(&migrant) {
+                warn!("Migrant dropped: {}", e);
+                continue;
+            }
+
+            migrant.reset_best_error(objective);
+
+            self.lineage_archive.insert(migrant.id.clone(), migrant.lineage().clone());
+            accepted.push(migrant);
+        }
+
+        self.networks.sort_by(|n1, n2| compare_errors(n1.best_error, n2.best_error));
+        let num_of_kept = self.networks.len().saturating_sub(accepted.len()).max(2);
+        self.networks.truncate(num_of_kept);
+        self.networks.extend(accepted);
+        self.networks.sort_by(|n1, n2| compare_errors(n1.best_error, n2.best_error));
+    }
+
+    // Call cancel() on the token (from any thread) to stop a running training
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation_token.clone()
+    }
+
+    fn archive_lineage(&mut self) {
+        for network in &self.networks {
+            self.lineage_archive.insert(network.id.clone(), network.lineage().clone());
+        }
+    }
+
+    // Works for current and removed networks
+    pub fn lineage(&self, id: &str) -> Option<&Lineage> {
+        self.networks.iter().find(|network| network.id == id).map(|network| network.lineage())
+            .or_else(|| self.lineage_archive.get(id))
+    }
+
+    // The network itself first, then its parent, grandparent, ...
+    pub fn ancestry(&self, id: &str) -> Vec<&Lineage> {
+        let mut result = Vec::new();
+        let mut current = self.lineage(id);
+
+        while let Some(lineage) = current {
+            result.push(lineage);
+            current = lineage.parent_id.as_ref().and_then(|parent_id| self.lineage(parent_id));
+
+            if result.len() > self.lineage_archive.len() + self.networks.len() {
+                // Broken lineage with a cycle
+                break
+            }
+        }
+
+        result
+    }
+
+    pub fn export_lineage<P: AsRef<Path>>(&self, filename: P) -> Result<(), Error> {
+        let mut lineages = self.lineage_archive.clone();
+
+        for network in &self.networks {
+            lineages.insert(network.id.clone(), network.lineage().clone());
+        }
+
+        let export = LineageExport { networks: lineages.into_values().collect() };
+        fs::write(filename, toml::Value::try_from(&export)?.to_string())?;
+
+        Ok(())
+    }
+
+    pub fn set_network(&mut self, network: Network) {
+        let id = network.id.clone();
+
+        if let Err(e) = self.set_network_with_mode(network, LoadMode::Repair) {
+            error!("Could not add network {}: {}", id, e);
+        }
+    }
+
+    pub fn set_network_with_mode(&mut self, mut network: Network, mode: LoadMode) -> Result<RepairReport, Error> {
         network.set_configuration(self.configuration.clone());
-        network.fix();
+        let num_of_nodes = network.num_of_nodes();
+        let report = prepare_network(&mut network, mode, num_of_nodes)?;
+
+        lineage::reserve_id(&network.id);
+        self.lineage_archive.insert(network.id.clone(), network.lineage().clone());
         self.networks.push(network);
+
+        Ok(report)
+    }
+
+    // Reuse a network trained for a different number of inputs / outputs
+    pub fn add_adapted_network(&mut self, property: &Property, mapping: &ShapeMapping, id: &str) -> Result<RepairReport, Error> {
+        let mut new_network = Network::new_with_property(self.configuration.clone(), property.adapt(mapping)?, id);
+        let report = prepare_network(&mut new_network, self.configuration.load_mode, property.nodes.len())?;
+
+        self.add_loaded_network(new_network, None);
+
+        Ok(report)
+    }
+
+    fn add_loaded_network(&mut self, mut network: Network, source: Option<&str>) {
+        lineage::reserve_id(&network.id);
+        network.lineage_mut().created_iteration = self.batch_iteration;
+        network.lineage_mut().source = source.map(|source| source.to_string());
+        self.lineage_archive.insert(network.id.clone(), network.lineage().clone());
+        self.networks.push(network);
+    }
+
+    // Frozen nodes keep their bias, weights and connections during training
+    pub fn freeze_nodes(&mut self, network_index: usize, start: usize, end: usize) {
+        assert!(network_index < self.networks.len());
+        self.networks[network_index].freeze_nodes(start, end);
+    }
+
+    pub fn unfreeze_nodes(&mut self, network_index: usize) {
+        assert!(network_index < self.networks.len());
+        self.networks[network_index].unfreeze_all();
     }
 
     pub fn load_network(&mut self, filename: &str, id: &str) -> Result<(), Error> {
+        let mode = self.configuration.load_mode;
+        self.load_network_with_mode(filename, id, mode)?;
+
+        Ok(())
+    }
+
+    // In strict mode a network that does not fit the configuration is rejected,
+    // in repair mode it is changed and the changes are returned.
+    pub fn load_network_with_mode(&mut self, filename: &str, id: &str, mode: LoadMode) -> Result<RepairReport, Error> {
         let data = fs::read_to_string(filename)?;
+        let property: Property = toml::from_str(&data)?;
+        let num_of_nodes = property.nodes.len();
+
+        let mut new_network = Network::new_with_property(self.configuration.clone(), property, id);
+        let report = prepare_network(&mut new_network, mode, num_of_nodes)?;
+
+        self.add_loaded_network(new_network, Some(filename));
+
+        Ok(report)
+    }
+
+    // Novelty of each network, the most novel behaviours are added to the archive
+    fn update_novelty(&mut self, probe_inputs: &[Vec<f64>]) -> Vec<f64> {
+        let descriptors: Vec<Vec<f64>> = self.networks.par_iter_mut().map(|network| novelty::sanitize(network.behaviour(probe_inputs))).collect();
+        let scores = novelty::novelty_scores(&descriptors, &self.novelty_archive, self.configuration.novelty_k);
+
+        let mut order: Vec<usize> = (0..descriptors.len()).collect();
+        order.sort_by(|i1, i2| compare_errors(scores[*i2], scores[*i1]));
+        for index in order.into_iter().take(self.configuration.novelty_archive_additions) {
+            self.novelty_archive.add(descriptors[index].clone(), self.configuration.max_novelty_archive_size);
+        }
+
+        scores
+    }
+
+    // Applies the configured stagnation actions, returns a description for the history
+    fn handle_stagnation(&mut self, rule: StagnationRule, batch_iteration: usize) -> String {
+        let actions: Vec<String> = self.configuration.stagnation_actions.iter().map(|action| action.to_string()).collect();
+        let description = format!("{}: {}", rule, actions.join(" + "));
+        info!("Stagnation in batch iteration {}, {}", batch_iteration, description);
+
+        // Keep the lineage of the networks that are replaced
+        self.archive_lineage();
+
+        for action in self.configuration.stagnation_actions.clone() {
+            match action {
+                StagnationAction::ReRandomizeBottomHalf => {
+                    let mut order: Vec<usize> = (0..self.networks.len()).collect();
+                    order.sort_by(|i1, i2| compare_errors(self.networks[*i1].best_error, self.networks[*i2].best_error));
+                    let num_of_kept = self.networks.len() - (self.networks.len() / 2);
+
+                    for index in order.into_iter().skip(num_of_kept.max(self.configuration.elitism)) {
+                        debug!("Replace network {} with a random network", self.networks[index].id);
+                        self.networks[index] = self.new_random_network(batch_iteration);
+                    }
+                }
+                StagnationAction::InjectNewNetworks => {
+                    for _ in 0..self.configuration.num_of_injected_networks {
+                        let network = self.new_random_network(batch_iteration);
+                        self.networks.push(network);
+                    }
+                }
+                StagnationAction::BoostMutation => {
+                    for network in self.networks.iter_mut().skip(1) {
+                        network.boost_mutation(self.configuration.mutation_boost);
+                        network.lineage_mut().add_event(batch_iteration, format!("mutation boosted by {}", self.configuration.mutation_boost));
+                    }
+                }
+            }
+        }
+
+        description
+    }
+
+    fn new_random_network(&self, batch_iteration: usize) -> Network {
+        let mut network = Network::new(self.configuration.clone());
+        network.lineage_mut().created_iteration = batch_iteration + 1;
+        network
+    }
+
+    pub fn novelty_archive(&self) -> &NoveltyArchive {
+        &self.novelty_archive
+    }
+
+    pub fn population_archive(&self, batch_iteration: Option<usize>) -> PopulationArchive {
+        PopulationArchive {
+            batch_iteration,
+            novelty_archive: self.novelty_archive.descriptors().to_vec(),
+            networks: self.networks.iter().map(|network| network.to_record()).collect(),
+        }
+    }
+
+    // Writes every network into its own file, together with an index file "population.toml"
+    pub fn save_population<P: AsRef<Path>>(&self, directory: P) -> Result<(), Error> {
+        self.population_archive(None).save_directory(directory)
+    }
+
+    // Writes all networks into a single file
+    pub fn save_population_archive<P: AsRef<Path>>(&self, filename: P) -> Result<(), Error> {
+        self.population_archive(None).save(filename)
+    }
 
-        let mut new_network = Network::new(self.configuration.clone());
-        new_network.set_property(toml::from_str(&data)?);
-        new_network.id = id.to_string();
-        new_network.fix();
+    pub fn load_population<P: AsRef<Path>>(&mut self, directory: P) -> Result<(), Error> {
+        let archive = PopulationArchive::load_directory(directory)?;
+        self.set_population(archive)
+    }
+
+    pub fn load_population_archive<P: AsRef<Path>>(&mut self, filename: P) -> Result<(), Error> {
+        let archive = PopulationArchive::load(filename)?;
+        self.set_population(archive)
+    }
+
+    // Replaces the current population
+    pub fn set_population(&mut self, archive: PopulationArchive) -> Result<(), Error> {
+        let mut networks = Vec::with_capacity(archive.networks.len());
 
-        self.networks.push(new_network);
+        for record in archive.networks {
+            let num_of_nodes = record.property.nodes.len();
+            let mut network = Network::from_record(self.configuration.clone(), record);
+            prepare_network(&mut network, self.configuration.load_mode, num_of_nodes)?;
+            networks.push(network);
+        }
+
+        if networks.len() < 2 {
+            bail!("Population must have at least two networks, given: {}", networks.len());
+        }
+
+        self.networks = networks;
+        self.novelty_archive = NoveltyArchive::new(archive.novelty_archive);
+        self.archive_lineage();
 
         Ok(())
     }
@@ -261,7 +1275,7 @@ impl Driver {
 
     pub fn save_network_with_index(&self, filename: &str, index: usize) -> Result<(), Error>  {
         assert!(index < self.networks.len());
-        let serialized = toml::Value::try_from(&self.networks[index].get_property())?.to_string();
+        let serialized = toml::Value::try_from(self.networks[index].get_property())?.to_string();
 
         let f = File::create(filename)?;
         let mut f = BufWriter::new(f);
@@ -273,5 +1287,398 @@ impl Driver {
 
 #[cfg(test)]
 mod test {
-    // use super::*;
+    use super::*;
+
+    use mutation::{NUM_OF_OPERATIONS};
+    use environment::{EpisodeRunner};
+    use control_benchmarks::{CartPole};
+    use test_support::{TempPath};
+
+    fn xor_configuration() -> DriverConfiguration {
+        toml::from_str("
+            num_of_input_nodes = 2
+            num_of_output_nodes = 1
+            num_of_networks = 4
+            num_of_node_mutation = 10
+            num_of_iterations = 10
+            num_of_batch_iterations = 3
+            batch_size = 4
+            use_trained_networks = false
+            node_threshold = 0.5
+            validate_mutations = true
+        ").unwrap()
+    }
+
+    fn xor_data() -> TrainingData {
+        TrainingData {
+            provided_input: vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]],
+            expected_output: vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]],
+        }
+    }
+
+    #[test]
+    fn nan_is_the_worst_error() {
+        let mut errors = [f64::NAN, 2.0, f64::MAX, 1.0];
+        errors.sort_by(|e1, e2| compare_errors(*e1, *e2));
+
+        assert_eq!(errors[..3], [1.0, 2.0, f64::MAX]);
+        assert!(errors[3].is_nan());
+    }
+
+    #[test]
+    fn strict_load_rejects_mismatch() {
+        let filename = TempPath::new("strict_load.toml");
+        fs::write(&filename, include_str!("trained_networks/adder05.toml")).unwrap();
+        let filename = filename.to_str().unwrap();
+
+        let mut configuration = xor_configuration();
+        configuration.max_network_size = 2;
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+
+        assert!(driver.load_network_with_mode(filename, "adder05", LoadMode::Strict).is_err());
+
+        let report = driver.load_network_with_mode(filename, "adder05", LoadMode::Repair).unwrap();
+        assert!(!report.is_empty());
+        assert_eq!(driver.networks.last().unwrap().num_of_nodes(), 2);
+    }
+
+    #[test]
+    fn population_round_trip() {
+        let directory = TempPath::new("driver_population");
+        let mut driver = Driver::new_from_config(xor_configuration()).unwrap();
+        driver.train(&xor_data()).unwrap();
+        driver.save_population(&directory).unwrap();
+
+        let mut loaded = Driver::new_from_config(xor_configuration()).unwrap();
+        loaded.load_population(&directory).unwrap();
+
+        assert_eq!(loaded.population_archive(None), driver.population_archive(None));
+    }
+
+    #[test]
+    fn train_records_operator_statistics() {
+        let mut driver = Driver::new_from_config(xor_configuration()).unwrap();
+        driver.train(&xor_data()).unwrap();
+
+        let statistics = driver.total_operator_statistics();
+        assert!(statistics.attempts.iter().sum::<u64>() > 0);
+        assert_eq!(driver.operation_probabilities(0).len(), NUM_OF_OPERATIONS);
+    }
+
+    #[test]
+    fn networks_have_unique_ids_and_lineage() {
+        let mut driver = Driver::new_from_config(xor_configuration()).unwrap();
+        let ids: Vec<String> = driver.networks.iter().map(|network| network.id.clone()).collect();
+        assert!(ids.iter().all(|id| id != "untrained"));
+        assert!(ids.iter().enumerate().all(|(i, id)| !ids[i + 1..].contains(id)));
+
+        driver.train(&xor_data()).unwrap();
+
+        let best_id = driver.networks[0].id.clone();
+        let lineage = driver.lineage(&best_id).unwrap();
+        assert!(!lineage.error_history.is_empty());
+        assert!(lineage.mutations_applied >= lineage.mutations_accepted);
+
+        let ancestry = driver.ancestry(&best_id);
+        assert_eq!(ancestry[0].id, best_id);
+        assert!(ancestry.last().unwrap().parent_id.is_none());
+
+        // Every initial network is still known, even if it was removed
+        for id in &ids {
+            assert!(driver.lineage(id).is_some());
+        }
+    }
+
+    #[test]
+    fn train_returns_history() {
+        let mut configuration = xor_configuration();
+        configuration.stop_at_desired_error = false;
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+        let history = driver.train(&xor_data()).unwrap();
+
+        assert_eq!(history.iterations.len(), 3);
+        for record in &history.iterations {
+            assert!(record.best_error <= record.median_error);
+            assert!(record.median_error <= record.worst_error);
+        }
+        assert_eq!(history.last().unwrap().best_id, driver.networks[0].id);
+    }
+
+    #[test]
+    fn training_stops_at_budget() {
+        let mut configuration = xor_configuration();
+        configuration.num_of_batch_iterations = 1000;
+        configuration.max_fitness_evaluations = Some(200);
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+
+        let history = driver.train(&xor_data()).unwrap();
+        assert_eq!(history.stop_reason, Some(StopReason::EvaluationBudget));
+        assert!(history.iterations.len() < 1000);
+        assert!(driver.networks.windows(2).all(|pair| compare_errors(pair[0].best_error, pair[1].best_error) != Ordering::Greater));
+
+        // A cancelled driver does not train until the token is reset
+        driver.configuration.max_fitness_evaluations = None;
+        driver.cancellation_token().cancel();
+        let history = driver.train(&xor_data()).unwrap();
+        assert_eq!(history.stop_reason, Some(StopReason::Cancelled));
+        assert!(history.iterations.is_empty());
+    }
+
+    #[test]
+    fn training_stops_at_desired_error() {
+        let mut configuration = xor_configuration();
+        configuration.num_of_batch_iterations = 100;
+        // Every network is good enough
+        configuration.desired_error = f64::MAX;
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+
+        let history = driver.train_with_validation(&xor_data(), Some(&xor_data())).unwrap();
+        assert_eq!(history.stop_reason, Some(StopReason::DesiredErrorReached));
+        assert_eq!(history.iterations.len(), 1);
+        assert!(history.iterations[0].full_error.is_some());
+    }
+
+    #[test]
+    fn train_with_fitness_function() {
+        let data = xor_data();
+        let fitness = |evaluator: &mut Evaluator| {
+            data.provided_input.iter().zip(&data.expected_output).map(|(input, expected)| {
+                (evaluator.calculate(input)[0] - expected[0]).abs()
+            }).sum::<f64>()
+        };
+
+        let mut configuration = xor_configuration();
+        configuration.stop_at_desired_error = false;
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+        let history = driver.train_with_fitness(&fitness).unwrap();
+
+        assert_eq!(history.iterations.len(), 3);
+        assert_eq!(history.stop_reason, Some(StopReason::Completed));
+
+        // The score of the best network is its error on the whole data set
+        let (error, _) = driver.test(&data.provided_input[0], &data.expected_output[0]);
+        assert!(error <= driver.networks[0].best_error);
+
+        // A negative score would always be good enough
+        assert!(driver.train_with_fitness(&|evaluator: &mut Evaluator| -1.0 - evaluator.calculate(&[0.0, 1.0])[0].abs()).is_err());
+    }
+
+    #[test]
+    fn train_cart_pole_controller() {
+        let mut configuration = xor_configuration();
+        configuration.num_of_input_nodes = 4;
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+
+        let runner = EpisodeRunner::new(CartPole::new(), 2, 1000);
+        let history = driver.train_with_fitness(&runner).unwrap();
+        assert_eq!(history.iterations.len(), 3);
+
+        // The score is the number of steps the pole was not balanced
+        let best_error = driver.networks[0].best_error;
+        assert!((0.0..=1000.0).contains(&best_error));
+        assert_eq!(runner.evaluate(&mut driver.evaluator(0)), best_error);
+    }
+
+    #[test]
+    fn selection_strategy_from_configuration() {
+        let mut configuration = xor_configuration();
+        configuration.selection = SelectionMethod::Tournament;
+        configuration.num_of_offspring = 2;
+        configuration.stop_at_desired_error = false;
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+
+        let history = driver.train(&xor_data()).unwrap();
+
+        for record in &history.iterations {
+            assert_eq!(record.population_size, 6);
+        }
+        assert_eq!(driver.networks.len(), 6);
+        assert_eq!(driver.lineage(&driver.networks[5].id).unwrap().origin, Origin::Clone);
+    }
+
+    #[test]
+    fn annealing_temperature_in_history() {
+        let mut configuration = xor_configuration();
+        configuration.acceptance = AcceptancePolicy::SimulatedAnnealing;
+        configuration.cooling_rate = 0.9;
+        configuration.stop_at_desired_error = false;
+        let mut driver = Driver::new_from_config(configuration.clone()).unwrap();
+
+        let history = driver.train(&xor_data()).unwrap();
+        let temperatures: Vec<f64> = history.iterations.iter().map(|record| record.temperature.unwrap()).collect();
+
+        assert!(temperatures.iter().all(|temperature| *temperature < configuration.initial_temperature));
+        // The best error is still the error of the kept network
+        let best_error = driver.networks[0].best_error;
+        assert!((driver.networks[0].calculate_batch_and_error(&[0, 1, 2, 3], &xor_data()) - best_error).abs() < 1e-9);
+
+        configuration.cooling_rate = 0.0;
+        assert!(configuration.validate().is_err());
+    }
+
+    #[test]
+    fn fine_tuning_after_and_during_training() {
+        let mut configuration = xor_configuration();
+        configuration.stop_at_desired_error = false;
+        configuration.fine_tuning_interval = 1;
+        configuration.num_of_gradient_steps = 10;
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+
+        driver.train(&xor_data()).unwrap();
+        let events: usize = driver.networks.iter().map(|network| {
+            network.lineage().events.iter().filter(|event| event.description.starts_with("fine-tuned")).count()
+        }).sum();
+        assert!(events > 0);
+
+        let best_error = driver.networks[0].best_error;
+        let (start_error, error) = driver.fine_tune(&xor_data());
+        assert!(error <= start_error);
+        assert!((driver.total_error(&xor_data()) - error).abs() < 1e-9);
+        // The whole training data is the batch, so the best error is the total error
+        assert!(driver.networks[0].best_error <= best_error);
+        assert!((driver.networks[0].best_error - error).abs() < 1e-9);
+    }
+
+    #[test]
+    fn fine_tuning_is_reverted_if_the_batch_error_goes_up() {
+        let mut configuration = xor_configuration();
+        configuration.num_of_gradient_steps = 10;
+        let data = xor_data();
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+        driver.train(&data).unwrap();
+
+        // A best error that no fine-tuning can reach
+        driver.networks[0].best_error = 0.0;
+        let property = driver.networks[0].get_property();
+
+        let (start_error, error) = driver.fine_tune(&data);
+        assert_eq!(error, start_error);
+        assert_eq!(driver.networks[0].get_property(), property);
+        assert_eq!(driver.networks[0].best_error, 0.0);
+    }
+
+    #[test]
+    fn evolution_strategy_keeps_structure() {
+        let mut configuration = xor_configuration();
+        configuration.initial_network_size = 3;
+        configuration.num_of_node_mutation = 40;
+        configuration.weight_optimizer = WeightOptimizer::SeparableNes;
+        let data = xor_data();
+        let objective = DataObjective {
+            training_data: &data,
+            validation_data: None,
+            indices: vec![0, 1, 2, 3],
+            change_batch: false,
+            rng: XorShiftRng::from_entropy(),
+        };
+
+        let mut network = Network::new(configuration.clone());
+        assert_eq!(network.weight_optimizer(), WeightOptimizer::SeparableNes);
+        network.freeze_nodes(0, 1);
+        let frozen_node = network.get_property().nodes[0].clone();
+        network.reset_best_error(&objective);
+        let start_error = network.best_error;
+        let structure: Vec<Vec<usize>> = network.get_property().nodes.iter().map(|node| node.connection_indices()).collect();
+
+        for _ in 0..3 {
+            network.optimize(&objective);
+        }
+
+        assert!(network.best_error <= start_error);
+        assert_eq!(objective.error(&mut network), network.best_error);
+        assert_eq!(network.get_property().nodes.iter().map(|node| node.connection_indices()).collect::<Vec<_>>(), structure);
+        assert_eq!(network.get_property().nodes[0], frozen_node);
+        assert!(network.lineage().mutations_applied > 0);
+
+        // Only the parameters of the two unfrozen nodes are searched
+        let num_of_free_parameters = network.get_property().nodes.iter().skip(1).map(|node| 1 + node.num_of_connections()).sum();
+        assert_eq!(network.to_record().state.nes_sigma.map(|sigma| sigma.len()), Some(num_of_free_parameters));
+
+        // The weight optimizer survives a reconfiguration and a checkpoint, a new choice could be different
+        configuration.weight_optimizer_share = 0.5;
+        network.set_configuration(configuration.clone());
+        assert_eq!(network.weight_optimizer(), WeightOptimizer::SeparableNes);
+        assert_eq!(Network::from_record(configuration, network.to_record()).weight_optimizer(), WeightOptimizer::SeparableNes);
+    }
+
+    #[test]
+    fn novelty_search_fills_archive() {
+        let mut configuration = xor_configuration();
+        configuration.novelty_weight = 0.5;
+        configuration.novelty_k = 3;
+        configuration.stop_at_desired_error = false;
+        let mut driver = Driver::new_from_config(configuration.clone()).unwrap();
+
+        let history = driver.train(&xor_data()).unwrap();
+        assert_eq!(driver.novelty_archive().len(), 3);
+        assert_eq!(driver.novelty_archive().descriptors()[0].len(), 4);
+        assert!(history.iterations.iter().all(|record| record.max_novelty.is_some()));
+        // predict() still uses the network with the lowest error
+        assert!(driver.networks.iter().all(|network| driver.networks[0].best_error <= network.best_error));
+
+        // The archive is part of the checkpoint
+        let mut loaded = Driver::new_from_config(configuration.clone()).unwrap();
+        loaded.set_population(driver.population_archive(None)).unwrap();
+        assert_eq!(loaded.novelty_archive(), driver.novelty_archive());
+
+        // A fitness function has no inputs that can be used as probes
+        let mut driver = Driver::new_from_config(configuration).unwrap();
+        assert!(driver.train_with_fitness(&|evaluator: &mut Evaluator| evaluator.calculate(&[0.0, 1.0])[0]).is_err());
+    }
+
+    #[test]
+    fn stagnation_triggers_actions() {
+        let mut configuration = xor_configuration();
+        configuration.num_of_batch_iterations = 4;
+        configuration.stop_at_desired_error = false;
+        // The first place counter of the best network reaches 1 in the first batch iteration
+        configuration.max_first_place_iterations = 1;
+        configuration.stagnation_actions = vec![StagnationAction::ReRandomizeBottomHalf, StagnationAction::InjectNewNetworks, StagnationAction::BoostMutation];
+        let mut driver = Driver::new_from_config(configuration.clone()).unwrap();
+
+        let history = driver.train(&xor_data()).unwrap();
+        let record = &history.iterations[0];
+
+        assert_eq!(record.stagnation.as_ref().unwrap(), "first_place: re_randomize_bottom_half + inject_new_networks + boost_mutation");
+        assert!(record.population_size >= 6);
+        assert!(driver.networks.iter().any(|network| network.lineage().events.iter().any(|event| event.description.starts_with("mutation boosted"))));
+        // Without self-adaptation the boost only changes the step scale, the hyperparameters are the configured ones
+        assert!(driver.networks.iter().all(|network| *network.hyperparameters() == Hyperparameters::new(&configuration)));
+        assert!(driver.networks.iter().any(|network| network.mutation_settings().step_scale > 1.0));
+
+        let origins: Vec<Origin> = driver.lineage_archive.values().map(|lineage| lineage.origin).collect();
+        assert!(origins.iter().filter(|origin| **origin == Origin::Random).count() > 4);
+    }
+
+    #[test]
+    fn hyperparameters_evolve_per_network() {
+        let mut configuration = xor_configuration();
+        configuration.num_of_batch_iterations = 10;
+        configuration.stop_at_desired_error = false;
+        configuration.self_adaptive_hyperparameters = true;
+        let mut driver = Driver::new_from_config(configuration.clone()).unwrap();
+        // Easier than xor, so that the networks improve and keep their hyperparameters
+        let data = TrainingData {
+            provided_input: vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]],
+            expected_output: vec![vec![0.0], vec![1.0], vec![2.0], vec![3.0]],
+        };
+
+        let history = driver.train(&data).unwrap();
+        assert!(history.iterations.iter().all(|record| record.mean_node_threshold.is_some() && record.mean_num_of_node_mutation.is_some() && record.mean_step_scale.is_some()));
+
+        let hyperparameters: Vec<Hyperparameters> = (0..driver.num_of_networks()).map(|index| driver.hyperparameters(index).clone()).collect();
+        assert!(hyperparameters.iter().any(|hyperparameters| *hyperparameters != Hyperparameters::new(&configuration)));
+
+        // The hyperparameters are part of the checkpoint
+        let mut loaded = Driver::new_from_config(configuration.clone()).unwrap();
+        loaded.set_population(driver.population_archive(None)).unwrap();
+        assert_eq!(loaded.hyperparameters(0), driver.hyperparameters(0));
+
+        // Without self-adaptation every network uses the configuration
+        configuration.self_adaptive_hyperparameters = false;
+        let mut driver = Driver::new_from_config(configuration.clone()).unwrap();
+        let history = driver.train(&data).unwrap();
+        assert!(history.iterations.iter().all(|record| record.mean_node_threshold.is_none()));
+        assert_eq!(driver.hyperparameters(0), &Hyperparameters::new(&configuration));
+    }
 }
@@ -0,0 +1,126 @@
+use rand::{XorShiftRng, SeedableRng};
+
+use fitness::{Evaluator, Fitness};
+
+// Result of one action
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Step {
+    pub reward: f64,
+    // The episode is over, no more steps possible
+    pub done: bool,
+}
+
+// A simulation that is controlled by a network: the observation is the input,
+// the outputs are the action.
+pub trait Environment {
+    fn num_of_observations(&self) -> usize;
+
+    fn num_of_actions(&self) -> usize;
+
+    // Highest reward of a single step, used to turn the rewards into a non-negative cost
+    fn max_reward_per_step(&self) -> f64;
+
+    // Start a new episode, the rng is used for the random start state
+    fn reset(&mut self, rng: &mut XorShiftRng);
+
+    fn observe(&self) -> Vec<f64>;
+
+    fn step(&mut self, action: &[f64]) -> Step;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EpisodeResult {
+    pub total_reward: f64,
+    pub num_of_steps: usize,
+    // false: stopped by max_steps
+    pub done: bool,
+}
+
+// The node values are kept between the steps, so the network can remember earlier observations.
+// Each action is calculated with steps_per_action calculations of the network.
+pub fn run_episode<E: Environment>(environment: &mut E, evaluator: &mut Evaluator, rng: &mut XorShiftRng, max_steps: usize, steps_per_action: usize) -> EpisodeResult {
+    assert_eq!(environment.num_of_observations(), evaluator.num_of_inputs(), "Number of observations must match the number of inputs");
+    assert_eq!(environment.num_of_actions(), evaluator.num_of_outputs(), "Number of actions must match the number of outputs");
+
+    environment.reset(rng);
+    evaluator.reset();
+
+    let mut result = EpisodeResult {
+        total_reward: 0.0,
+        num_of_steps: 0,
+        done: false,
+    };
+
+    while result.num_of_steps < max_steps {
+        let observation = environment.observe();
+        let mut action = evaluator.step(&observation);
+
+        for _ in 1..steps_per_action {
+            action = evaluator.step(&observation);
+        }
+
+        let step = environment.step(&action);
+        result.total_reward += step.reward;
+        result.num_of_steps += 1;
+
+        if step.done {
+            result.done = true;
+            break
+        }
+    }
+
+    result
+}
+
+fn episode_rng(seed: u64, episode: usize) -> XorShiftRng {
+    let mut bytes = [0; 16];
+    bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    bytes[8..].copy_from_slice(&(episode as u64).to_le_bytes());
+    // XorShiftRng must not be seeded with all zeros
+    bytes[15] |= 0x80;
+
+    XorShiftRng::from_seed(bytes)
+}
+
+// Fitness function that runs a number of episodes and returns the mean cost of an episode:
+// the reward that was missed compared to max_reward_per_step in each of the max_steps steps.
+// The cost is >= 0 and 0 only for a perfect episode, like an error.
+// Every evaluation uses the same start states, so the scores of different networks can be compared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpisodeRunner<E> {
+    pub environment: E,
+    pub num_of_episodes: usize,
+    pub max_steps: usize,
+    pub steps_per_action: usize,
+    pub seed: u64,
+}
+
+impl<E: Environment + Clone + Sync> EpisodeRunner<E> {
+    pub fn new(environment: E, num_of_episodes: usize, max_steps: usize) -> EpisodeRunner<E> {
+        assert!(num_of_episodes > 0);
+
+        EpisodeRunner {
+            environment,
+            num_of_episodes,
+            max_steps,
+            steps_per_action: 1,
+            seed: 1,
+        }
+    }
+
+    pub fn run(&self, evaluator: &mut Evaluator) -> Vec<EpisodeResult> {
+        (0..self.num_of_episodes).map(|episode| {
+            let mut environment = self.environment.clone();
+            let mut rng = episode_rng(self.seed, episode);
+            run_episode(&mut environment, evaluator, &mut rng, self.max_steps, self.steps_per_action)
+        }).collect()
+    }
+}
+
+impl<E: Environment + Clone + Sync> Fitness for EpisodeRunner<E> {
+    fn evaluate(&self, evaluator: &mut Evaluator) -> f64 {
+        let max_reward = (self.max_steps as f64) * self.environment.max_reward_per_step();
+        let total_cost: f64 = self.run(evaluator).iter().map(|result| (max_reward - result.total_reward).max(0.0)).sum();
+        total_cost / (self.num_of_episodes as f64)
+    }
+}
@@ -0,0 +1,97 @@
+use network::{Network, worst_if_not_finite};
+
+// Gives a fitness function access to the outputs of a network without changing the network
+pub struct Evaluator<'a> {
+    network: &'a mut Network,
+}
+
+impl<'a> Evaluator<'a> {
+    pub(crate) fn new(network: &'a mut Network) -> Evaluator<'a> {
+        Evaluator {
+            network,
+        }
+    }
+
+    pub fn num_of_inputs(&self) -> usize {
+        self.network.num_of_inputs()
+    }
+
+    pub fn num_of_outputs(&self) -> usize {
+        self.network.num_of_outputs()
+    }
+
+    // Stateless: all node values are reset and num_of_cycles calculations are done
+    pub fn calculate(&mut self, provided_input: &[f64]) -> Vec<f64> {
+        assert_eq!(provided_input.len(), self.num_of_inputs(), "Wrong number of inputs");
+        self.network.calculate(provided_input);
+        self.network.get_output()
+    }
+
+    // Stateful: one calculation that starts with the node values of the previous step
+    pub fn step(&mut self, provided_input: &[f64]) -> Vec<f64> {
+        assert_eq!(provided_input.len(), self.num_of_inputs(), "Wrong number of inputs");
+        self.network.calculate_once(provided_input);
+        self.network.get_output()
+    }
+
+    // Sets all node values to zero, for example at the start of an episode
+    pub fn reset(&mut self) {
+        self.network.reset_values();
+    }
+}
+
+// Scores a network like the error for training data: lower is better, the score must be >= 0 and
+// desired_error is the target. Turn a reward into a cost, for example max_reward - reward.
+// Training stops with an error if a score is negative.
+pub trait Fitness: Sync {
+    fn evaluate(&self, evaluator: &mut Evaluator) -> f64;
+}
+
+impl<F> Fitness for F where F: Fn(&mut Evaluator) -> f64 + Sync {
+    fn evaluate(&self, evaluator: &mut Evaluator) -> f64 {
+        self(evaluator)
+    }
+}
+
+// What the population loop optimizes: training data or a fitness function
+pub(crate) trait Objective: Sync {
+    // Called before each batch iteration
+    fn next_batch(&mut self) {}
+
+    // Only for labelled data, the batch can then be sent to other processes
+    fn batch_indices(&self) -> Option<&[usize]> {
+        None
+    }
+
+    // Default inputs for the behaviour descriptors of novelty search
+    fn probe_inputs(&self) -> Option<&[Vec<f64>]> {
+        None
+    }
+
+    // Must be >= 0, 0 is a perfect network
+    fn error(&self, network: &mut Network) -> f64;
+
+    // Gradient fine-tuning of the weights on the current batch, sets the best error.
+    // Returns the errors before and after, None if the objective has no gradient.
+    fn fine_tune(&self, _network: &mut Network) -> Option<(f64, f64)> {
+        None
+    }
+
+    // Compared with desired_error to stop the whole training
+    fn full_error(&self, network: &mut Network) -> f64;
+}
+
+pub(crate) struct FitnessObjective<'a, F: 'a + Fitness> {
+    pub fitness: &'a F,
+}
+
+impl<'a, F: 'a + Fitness> Objective for FitnessObjective<'a, F> {
+    fn error(&self, network: &mut Network) -> f64 {
+        worst_if_not_finite(self.fitness.evaluate(&mut Evaluator::new(network)))
+    }
+
+    fn full_error(&self, network: &mut Network) -> f64 {
+        // The fitness function may be stochastic, so evaluate again
+        self.error(network)
+    }
+}
@@ -0,0 +1,347 @@
+// Fine-tuning of the weights and biases of a fixed network structure with backpropagation through time.
+//
+// Network::calculate() resets all values and then updates the nodes num_of_cycles times in place:
+// a node reads the values of the nodes before it from the current cycle and the values of itself and
+// the nodes after it from the previous cycle. The backward pass replays these updates in reverse order.
+//
+// Parameters are stored node by node: bias, then the weight of each connection.
+
+use std::f64;
+
+use driver::{DriverConfiguration, TrainingData};
+use property::{Property};
+use mutation::{ConfigurationError};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GradientOptimizer {
+    Sgd,
+    Adam,
+}
+
+const ADAM_BETA1: f64 = 0.9;
+const ADAM_BETA2: f64 = 0.999;
+const ADAM_EPSILON: f64 = 1e-8;
+
+pub fn validate(configuration: &DriverConfiguration) -> Result<(), ConfigurationError> {
+    if configuration.learning_rate.is_finite() && configuration.learning_rate > 0.0 {
+        Ok(())
+    } else {
+        Err(ConfigurationError { errors: vec![format!("learning_rate must be finite and > 0, given: {}", configuration.learning_rate)] })
+    }
+}
+
+// Derivative of the leaky ReLU, a node value is negative exactly if the weighted sum was negative
+fn derivative(value: f64) -> f64 {
+    if value < 0.0 {
+        0.01
+    } else {
+        1.0
+    }
+}
+
+// Position of the bias of each node in the parameter vector
+fn parameter_offsets(property: &Property) -> Vec<usize> {
+    let mut offset = 0;
+
+    property.nodes.iter().map(|node| {
+        let result = offset;
+        offset += 1 + node.num_of_connections();
+        result
+    }).collect()
+}
+
+pub fn parameters(property: &Property) -> Vec<f64> {
+    let mut result = Vec::new();
+
+    for node in &property.nodes {
+        result.push(node.bias());
+        result.extend((0..node.num_of_connections()).map(|position| node.connection(position).1));
+    }
+
+    result
+}
+
+// Positions of the parameters of all unfrozen nodes
+pub fn free_parameter_indices(property: &Property) -> Vec<usize> {
+    property.nodes.iter().zip(parameter_offsets(property)).filter(|(node, _)| !node.is_frozen()).flat_map(|(node, offset)| {
+        offset..(offset + 1 + node.num_of_connections())
+    }).collect()
+}
+
+// Frozen nodes keep their bias and weights
+pub fn set_parameters(property: &mut Property, parameters: &[f64]) {
+    let offsets = parameter_offsets(property);
+
+    for (node, offset) in property.nodes.iter_mut().zip(offsets) {
+        if node.is_frozen() {
+            continue;
+        }
+
+        node.set_bias(parameters[offset]);
+        for position in 0..node.num_of_connections() {
+            let (index, _) = node.connection(position);
+            node.set_connection(position, index, parameters[offset + 1 + position]);
+        }
+    }
+}
+
+// Same calculation as Network::calculate(), returns the values after each cycle.
+// The first entry is the initial state (all zero).
+fn forward(property: &Property, num_of_inputs: usize, num_of_cycles: usize, provided_input: &[f64]) -> Vec<Vec<f64>> {
+    let mut values = vec![0.0; num_of_inputs + property.nodes.len()];
+    let mut states = Vec::with_capacity(num_of_cycles + 1);
+    states.push(values.clone());
+
+    for _ in 0..num_of_cycles {
+        values[..num_of_inputs].copy_from_slice(&provided_input[..num_of_inputs]);
+
+        for (i, node) in property.nodes.iter().enumerate() {
+            values[num_of_inputs + i] = node.calculate(&values);
+        }
+
+        states.push(values.clone());
+    }
+
+    states
+}
+
+// Adds the gradient of the absolute error of one entry to gradient, returns the error
+pub fn backpropagate(property: &Property, num_of_inputs: usize, num_of_cycles: usize, provided_input: &[f64], expected_output: &[f64], gradient: &mut [f64]) -> f64 {
+    let states = forward(property, num_of_inputs, num_of_cycles, provided_input);
+    let offsets = parameter_offsets(property);
+    let last = &states[num_of_cycles];
+
+    // Gradient of the error with respect to the current content of each value
+    let mut adjoint = vec![0.0; last.len()];
+    let mut error = 0.0;
+
+    for (index, expected_value) in property.output_indices.iter().zip(expected_output) {
+        let difference = last[*index] - expected_value;
+        error += difference.abs();
+
+        if difference > 0.0 {
+            adjoint[*index] += 1.0;
+        } else if difference < 0.0 {
+            adjoint[*index] -= 1.0;
+        }
+    }
+
+    for cycle in (0..num_of_cycles).rev() {
+        for (i, node) in property.nodes.iter().enumerate().rev() {
+            let value_index = num_of_inputs + i;
+            let value_adjoint = adjoint[value_index];
+            // The value before this update is a different variable
+            adjoint[value_index] = 0.0;
+
+            if value_adjoint == 0.0 {
+                continue;
+            }
+
+            let sum_adjoint = value_adjoint * derivative(states[cycle + 1][value_index]);
+            let offset = offsets[i];
+            gradient[offset] += sum_adjoint;
+
+            for position in 0..node.num_of_connections() {
+                let (index, weight) = node.connection(position);
+                // Inputs and nodes before this one were already updated in this cycle
+                let read_value = if index < value_index { states[cycle + 1][index] } else { states[cycle][index] };
+
+                gradient[offset + 1 + position] += sum_adjoint * read_value;
+                adjoint[index] += sum_adjoint * weight;
+            }
+        }
+    }
+
+    error
+}
+
+// Error and gradient over the given entries
+pub fn error_and_gradient(property: &Property, num_of_inputs: usize, num_of_cycles: usize, indices: &[usize], training_data: &TrainingData) -> (f64, Vec<f64>) {
+    let num_of_parameters = property.nodes.iter().map(|node| 1 + node.num_of_connections()).sum();
+    let mut gradient = vec![0.0; num_of_parameters];
+
+    let error = indices.iter().fold(0.0, |error, index| {
+        error + backpropagate(property, num_of_inputs, num_of_cycles, &training_data.provided_input[*index], &training_data.expected_output[*index], &mut gradient)
+    });
+
+    (error, gradient)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientDescent {
+    optimizer: GradientOptimizer,
+    learning_rate: f64,
+    // Adam moment estimates
+    first_moment: Vec<f64>,
+    second_moment: Vec<f64>,
+    num_of_steps: i32,
+}
+
+impl GradientDescent {
+    pub fn new(optimizer: GradientOptimizer, learning_rate: f64, num_of_parameters: usize) -> GradientDescent {
+        GradientDescent {
+            optimizer,
+            learning_rate,
+            first_moment: vec![0.0; num_of_parameters],
+            second_moment: vec![0.0; num_of_parameters],
+            num_of_steps: 0,
+        }
+    }
+
+    pub fn step(&mut self, parameters: &mut [f64], gradient: &[f64]) {
+        self.num_of_steps += 1;
+
+        match self.optimizer {
+            GradientOptimizer::Sgd => {
+                for (parameter, gradient) in parameters.iter_mut().zip(gradient) {
+                    *parameter -= self.learning_rate * gradient;
+                }
+            }
+            GradientOptimizer::Adam => {
+                let correction1 = 1.0 - ADAM_BETA1.powi(self.num_of_steps);
+                let correction2 = 1.0 - ADAM_BETA2.powi(self.num_of_steps);
+
+                for (i, (parameter, gradient)) in parameters.iter_mut().zip(gradient).enumerate() {
+                    self.first_moment[i] = (ADAM_BETA1 * self.first_moment[i]) + ((1.0 - ADAM_BETA1) * gradient);
+                    self.second_moment[i] = (ADAM_BETA2 * self.second_moment[i]) + ((1.0 - ADAM_BETA2) * gradient * gradient);
+
+                    let first = self.first_moment[i] / correction1;
+                    let second = self.second_moment[i] / correction2;
+                    *parameter -= self.learning_rate * first / (second.sqrt() + ADAM_EPSILON);
+                }
+            }
+        }
+    }
+}
+
+// Runs num_of_gradient_steps steps over the given entries and keeps the parameters with the lowest error.
+// Returns the error before and after fine-tuning.
+pub fn fine_tune(property: &mut Property, configuration: &DriverConfiguration, indices: &[usize], training_data: &TrainingData) -> (f64, f64) {
+    let num_of_inputs = configuration.num_of_input_nodes;
+    let num_of_cycles = configuration.num_of_cycles;
+    let num_of_steps = configuration.num_of_gradient_steps;
+    let mut parameters = parameters(property);
+    let mut descent = GradientDescent::new(configuration.gradient_optimizer, configuration.learning_rate, parameters.len());
+    let mut best_parameters = parameters.clone();
+    let mut start_error = None;
+    let mut best_error = f64::MAX;
+
+    // One more pass than steps, to evaluate the last step
+    for step in 0..=num_of_steps {
+        let (error, gradient) = error_and_gradient(property, num_of_inputs, num_of_cycles, indices, training_data);
+
+        if !error.is_finite() || gradient.iter().any(|value| !value.is_finite()) {
+            break;
+        }
+
+        start_error.get_or_insert(error);
+
+        if error < best_error {
+            best_error = error;
+            best_parameters.copy_from_slice(&parameters);
+        }
+
+        if step == num_of_steps {
+            break;
+        }
+
+        descent.step(&mut parameters, &gradient);
+        set_parameters(property, &parameters);
+    }
+
+    set_parameters(property, &best_parameters);
+
+    match start_error {
+        Some(start_error) => (start_error, best_error),
+        None => (f64::MAX, f64::MAX),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use node::{Node};
+    use toml;
+
+    // Two nodes with a recurrent connection, the output reads the second node
+    fn property() -> Property {
+        let mut node1 = Node::new(0.1);
+        node1.add_connection(0, 0.5);
+        node1.add_connection(1, -0.3);
+        node1.add_connection(3, 0.2);
+
+        let mut node2 = Node::new(-0.2);
+        node2.add_connection(2, 0.7);
+        node2.add_connection(3, 0.4);
+        node2.add_connection(0, -0.6);
+
+        Property {
+            nodes: vec![node1, node2],
+            output_indices: vec![3],
+        }
+    }
+
+    fn data() -> TrainingData {
+        TrainingData {
+            provided_input: vec![vec![0.5, 1.0], vec![1.0, -0.5], vec![-1.0, 0.25]],
+            expected_output: vec![vec![1.0], vec![0.2], vec![-0.3]],
+        }
+    }
+
+    #[test]
+    fn gradient_matches_finite_differences() {
+        let property = property();
+        let data = data();
+        let indices = [0, 1, 2];
+        let (_, gradient) = error_and_gradient(&property, 2, 3, &indices, &data);
+        let start = parameters(&property);
+        let epsilon = 1e-6;
+
+        for i in 0..start.len() {
+            let mut changed = property.clone();
+            let mut values = start.clone();
+
+            values[i] += epsilon;
+            set_parameters(&mut changed, &values);
+            let (error_plus, _) = error_and_gradient(&changed, 2, 3, &indices, &data);
+
+            values[i] -= 2.0 * epsilon;
+            set_parameters(&mut changed, &values);
+            let (error_minus, _) = error_and_gradient(&changed, 2, 3, &indices, &data);
+
+            let numeric = (error_plus - error_minus) / (2.0 * epsilon);
+            assert!((numeric - gradient[i]).abs() < 1e-4, "Parameter {}: {} != {}", i, numeric, gradient[i]);
+        }
+    }
+
+    fn configuration(optimizer: GradientOptimizer, num_of_gradient_steps: usize) -> DriverConfiguration {
+        let mut configuration: DriverConfiguration = toml::from_str("
+            num_of_input_nodes = 2
+            num_of_output_nodes = 1
+            num_of_cycles = 3
+        ").unwrap();
+        configuration.gradient_optimizer = optimizer;
+        configuration.num_of_gradient_steps = num_of_gradient_steps;
+        configuration
+    }
+
+    #[test]
+    fn fine_tuning_reduces_error() {
+        for optimizer in &[GradientOptimizer::Sgd, GradientOptimizer::Adam] {
+            let mut property = property();
+            let (start_error, error) = fine_tune(&mut property, &configuration(*optimizer, 50), &[0, 1, 2], &data());
+
+            assert!(error < start_error);
+            assert_eq!(error_and_gradient(&property, 2, 3, &[0, 1, 2], &data()).0, error);
+        }
+
+        // Frozen nodes do not change
+        let mut property = property();
+        property.freeze_nodes(0, 1);
+        let frozen_node = property.nodes[0].clone();
+        fine_tune(&mut property, &configuration(GradientOptimizer::Adam, 10), &[0, 1, 2], &data());
+        assert_eq!(property.nodes[0], frozen_node);
+    }
+}
@@ -0,0 +1,218 @@
+use std::fs;
+use std::path::{Path};
+use std::fmt::Write;
+
+use failure::Error;
+use serde_json;
+
+use stopping::{StopReason};
+
+// State of the population at the end of one batch iteration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IterationRecord {
+    pub batch_iteration: usize,
+    // Seconds since the Unix epoch
+    pub timestamp: f64,
+    // Seconds since the start of this training run
+    pub elapsed_seconds: f64,
+    pub best_error: f64,
+    pub median_error: f64,
+    pub worst_error: f64,
+    // Error of the best network on the full training set or the validation set
+    #[serde(default)]
+    pub full_error: Option<f64>,
+    pub best_id: String,
+    pub min_num_of_nodes: usize,
+    pub max_num_of_nodes: usize,
+    pub mean_num_of_nodes: f64,
+    pub population_size: usize,
+    // Network that maybe_add_node() was called for and if a node was added
+    pub add_node_target: Option<String>,
+    pub node_added: bool,
+    // Ids of the parent and of the first copy made by the selection
+    pub cloned_id: Option<String>,
+    pub clone_id: Option<String>,
+    #[serde(default)]
+    pub num_of_copies: usize,
+    // Temperature (or threshold) of the best network's acceptance policy
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    // Highest novelty in the population, only for novelty search
+    #[serde(default)]
+    pub max_novelty: Option<f64>,
+    // Stagnation rule that fired and the actions taken
+    #[serde(default)]
+    pub stagnation: Option<String>,
+    // Population means of the hyperparameters, only if they are self-adaptive
+    #[serde(default)]
+    pub mean_node_threshold: Option<f64>,
+    #[serde(default)]
+    pub mean_num_of_node_mutation: Option<f64>,
+    #[serde(default)]
+    pub mean_step_scale: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct TrainingHistory {
+    pub iterations: Vec<IterationRecord>,
+    #[serde(default)]
+    pub stop_reason: Option<StopReason>,
+    // Number of batch error calculations during the whole training
+    #[serde(default)]
+    pub num_of_evaluations: usize,
+}
+
+const CSV_HEADER: &str = "batch_iteration,timestamp,elapsed_seconds,best_error,median_error,worst_error,full_error,best_id,min_num_of_nodes,max_num_of_nodes,mean_num_of_nodes,population_size,add_node_target,node_added,cloned_id,clone_id,num_of_copies,temperature,max_novelty,stagnation,mean_node_threshold,mean_num_of_node_mutation,mean_step_scale";
+
+// Errors must already be sorted, best first
+pub fn median(sorted_errors: &[f64]) -> f64 {
+    let len = sorted_errors.len();
+
+    if len == 0 {
+        0.0
+    } else if len % 2 == 1 {
+        sorted_errors[len / 2]
+    } else {
+        (sorted_errors[(len / 2) - 1] + sorted_errors[len / 2]) * 0.5
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains(',') || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl TrainingHistory {
+    pub fn push(&mut self, record: IterationRecord) {
+        self.iterations.push(record);
+    }
+
+    // Adds the iterations of a later training run
+    pub fn append(&mut self, other: TrainingHistory) {
+        self.iterations.extend(other.iterations);
+        self.stop_reason = other.stop_reason;
+        self.num_of_evaluations += other.num_of_evaluations;
+    }
+
+    pub fn last(&self) -> Option<&IterationRecord> {
+        self.iterations.last()
+    }
+
+    pub fn best_errors(&self) -> Vec<f64> {
+        self.iterations.iter().map(|record| record.best_error).collect()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut result = String::new();
+        result.push_str(CSV_HEADER);
+        result.push('\n');
+
+        for record in &self.iterations {
+            // Writing into a String can not fail
+            let _ = writeln!(result, "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                record.batch_iteration,
+                record.timestamp,
+                record.elapsed_seconds,
+                record.best_error,
+                record.median_error,
+                record.worst_error,
+                record.full_error.map_or(String::new(), |error| error.to_string()),
+                csv_field(&record.best_id),
+                record.min_num_of_nodes,
+                record.max_num_of_nodes,
+                record.mean_num_of_nodes,
+                record.population_size,
+                csv_field(record.add_node_target.as_ref().map_or("", |id| id)),
+                record.node_added,
+                csv_field(record.cloned_id.as_ref().map_or("", |id| id)),
+                csv_field(record.clone_id.as_ref().map_or("", |id| id)),
+                record.num_of_copies,
+                record.temperature.map_or(String::new(), |temperature| temperature.to_string()),
+                record.max_novelty.map_or(String::new(), |novelty| novelty.to_string()),
+                csv_field(record.stagnation.as_ref().map_or("", |stagnation| stagnation)),
+                record.mean_node_threshold.map_or(String::new(), |value| value.to_string()),
+                record.mean_num_of_node_mutation.map_or(String::new(), |value| value.to_string()),
+                record.mean_step_scale.map_or(String::new(), |value| value.to_string()));
+        }
+
+        result
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn save_csv<P: AsRef<Path>>(&self, filename: P) -> Result<(), Error> {
+        fs::write(filename, self.to_csv())?;
+        Ok(())
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, filename: P) -> Result<(), Error> {
+        fs::write(filename, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn history() -> TrainingHistory {
+        let mut history = TrainingHistory::default();
+
+        history.push(IterationRecord {
+            batch_iteration: 0,
+            timestamp: 1500000000.5,
+            elapsed_seconds: 0.25,
+            best_error: 0.5,
+            median_error: 1.0,
+            worst_error: 2.0,
+            full_error: None,
+            best_id: "xor02-3".to_string(),
+            min_num_of_nodes: 2,
+            max_num_of_nodes: 5,
+            mean_num_of_nodes: 3.5,
+            population_size: 5,
+            add_node_target: Some("n7".to_string()),
+            node_added: true,
+            cloned_id: Some("xor02-3".to_string()),
+            clone_id: Some("n9".to_string()),
+            num_of_copies: 1,
+            temperature: Some(0.05),
+            max_novelty: None,
+            stagnation: Some("no_improvement: boost_mutation + inject_new_networks".to_string()),
+            mean_node_threshold: Some(0.15),
+            mean_num_of_node_mutation: Some(80.5),
+            mean_step_scale: Some(2.0),
+        });
+
+        history
+    }
+
+    #[test]
+    fn median_of_even_and_odd_length() {
+        assert_eq!(median(&[1.0, 2.0, 4.0]), 2.0);
+        assert_eq!(median(&[1.0, 2.0, 4.0, 8.0]), 3.0);
+    }
+
+    #[test]
+    fn csv_has_one_line_per_iteration() {
+        let csv = history().to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].split(',').count(), lines[1].split(',').count());
+        assert!(lines[1].starts_with("0,1500000000.5,0.25,0.5,1,2,,xor02-3,"));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = history().to_json().unwrap();
+        let loaded: TrainingHistory = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded, history());
+    }
+}
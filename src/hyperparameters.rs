@@ -0,0 +1,88 @@
+// Mutation parameters that every network carries itself. With self_adaptive_hyperparameters they are
+// mutated together with the property and survive if the network improves (self-adaptive EA),
+// otherwise they are the values from the DriverConfiguration.
+// The step scale is no field of its own: the self-adaptation mutates MutationSettings::step_scale,
+// the single factor for the delta limits that the 1/5th success rule and BoostMutation change as well.
+
+use rand::{Rng};
+use rand::distributions::{StandardNormal};
+
+use driver::{DriverConfiguration};
+use mutation::{ConfigurationError, MIN_STEP_SCALE, MAX_STEP_SCALE};
+
+const MIN_NODE_THRESHOLD: f64 = 0.01;
+const MAX_NODE_THRESHOLD: f64 = 0.99;
+// Upper limit for num_of_node_mutation, relative to the configured value
+const MAX_NODE_MUTATION_FACTOR: usize = 10;
+
+pub fn validate(configuration: &DriverConfiguration) -> Result<(), ConfigurationError> {
+    let rate = configuration.hyperparameter_learning_rate;
+
+    if rate.is_finite() && rate >= 0.0 {
+        Ok(())
+    } else {
+        Err(ConfigurationError { errors: vec![format!("hyperparameter_learning_rate must be finite and >= 0, given: {}", rate)] })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hyperparameters {
+    // Probability of a node mutation instead of a property mutation
+    pub node_threshold: f64,
+    pub num_of_node_mutation: usize,
+}
+
+impl Hyperparameters {
+    pub fn new(configuration: &DriverConfiguration) -> Hyperparameters {
+        Hyperparameters {
+            node_threshold: configuration.node_threshold,
+            num_of_node_mutation: configuration.num_of_node_mutation,
+        }
+    }
+
+    // Log-normal mutation, the node threshold is changed in logit space so it stays a probability.
+    // The step scale of the network's MutationSettings is mutated together with the hyperparameters.
+    pub fn mutate<R: Rng>(&mut self, rng: &mut R, configuration: &DriverConfiguration, step_scale: &mut f64) {
+        let rate = configuration.hyperparameter_learning_rate;
+        let mut factor = || (rate * rng.sample(StandardNormal)).exp();
+
+        let threshold = self.node_threshold.clamp(MIN_NODE_THRESHOLD, MAX_NODE_THRESHOLD);
+        let odds = (threshold / (1.0 - threshold)) * factor();
+        self.node_threshold = (odds / (1.0 + odds)).clamp(MIN_NODE_THRESHOLD, MAX_NODE_THRESHOLD);
+
+        let max_num_of_node_mutation = (configuration.num_of_node_mutation * MAX_NODE_MUTATION_FACTOR).max(1);
+        let num_of_node_mutation = ((self.num_of_node_mutation as f64) * factor()).round() as usize;
+        self.num_of_node_mutation = num_of_node_mutation.clamp(1, max_num_of_node_mutation);
+
+        *step_scale = (*step_scale * factor()).clamp(MIN_STEP_SCALE, MAX_STEP_SCALE);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::{XorShiftRng, SeedableRng};
+    use toml;
+
+    #[test]
+    fn mutation_stays_in_bounds() {
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+        let mut configuration: DriverConfiguration = toml::from_str("num_of_node_mutation = 5").unwrap();
+        configuration.hyperparameter_learning_rate = 2.0;
+        let mut hyperparameters = Hyperparameters::new(&configuration);
+        let mut step_scale = 1.0;
+        let mut changed = false;
+
+        for _ in 0..1000 {
+            hyperparameters.mutate(&mut rng, &configuration, &mut step_scale);
+            changed |= hyperparameters != Hyperparameters::new(&configuration);
+
+            assert!(hyperparameters.node_threshold >= MIN_NODE_THRESHOLD && hyperparameters.node_threshold <= MAX_NODE_THRESHOLD);
+            assert!(hyperparameters.num_of_node_mutation >= 1 && hyperparameters.num_of_node_mutation <= 50);
+            assert!((MIN_STEP_SCALE..=MAX_STEP_SCALE).contains(&step_scale));
+        }
+
+        assert!(changed);
+    }
+}
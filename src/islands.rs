@@ -0,0 +1,302 @@
+use std::fs;
+
+use failure::Error;
+use rayon::prelude::*;
+use toml;
+
+use driver::{Driver, DriverConfiguration, TrainingData, compare_errors};
+use fitness::{Fitness, FitnessObjective};
+use history::{TrainingHistory};
+use lineage::{Lineage};
+use network::{Network};
+use stopping::{CancellationToken, StopReason};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationTopology {
+    // Island i sends its migrants to island i + 1
+    Ring,
+    // Every island sends its migrants to all other islands
+    FullyConnected,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ArchipelagoConfiguration {
+    #[serde(default="ArchipelagoConfiguration::topology")]
+    pub topology: MigrationTopology,
+    // One epoch is one call of Driver::train() for every island
+    #[serde(default="ArchipelagoConfiguration::num_of_epochs")]
+    pub num_of_epochs: usize,
+    // Migrate after every n epochs
+    #[serde(default="ArchipelagoConfiguration::migration_interval")]
+    pub migration_interval: usize,
+    // Number of networks each island sends to each of its neighbours
+    #[serde(default="ArchipelagoConfiguration::num_of_migrants")]
+    pub num_of_migrants: usize,
+    // Settings for each island, all islands must have the same number of inputs and outputs
+    pub islands: Vec<DriverConfiguration>,
+}
+
+impl ArchipelagoConfiguration {
+    fn topology() -> MigrationTopology {MigrationTopology::Ring}
+    fn num_of_epochs() -> usize {10}
+    fn migration_interval() -> usize {1}
+    fn num_of_migrants() -> usize {1}
+}
+
+// Islands that evolve in parallel and exchange their best networks
+#[derive(Debug, Clone)]
+pub struct Archipelago {
+    configuration: ArchipelagoConfiguration,
+    islands: Vec<Driver>,
+    // Island with the best network, used by predict()
+    best_island: usize,
+    cancellation_token: CancellationToken,
+}
+
+impl Archipelago {
+    pub fn new(configuration: ArchipelagoConfiguration) -> Result<Archipelago, Error> {
+        if configuration.islands.is_empty() {
+            bail!("Archipelago needs at least one island");
+        }
+
+        if configuration.migration_interval == 0 {
+            bail!("migration_interval must be > 0");
+        }
+
+        let first = &configuration.islands[0];
+        for (index, island) in configuration.islands.iter().enumerate() {
+            if island.num_of_input_nodes != first.num_of_input_nodes || island.num_of_output_nodes != first.num_of_output_nodes {
+                bail!("Island {} has a different number of inputs or outputs", index);
+            }
+        }
+
+        let cancellation_token = CancellationToken::new();
+        let mut islands = Vec::with_capacity(configuration.islands.len());
+        for island_configuration in &configuration.islands {
+            let mut island = Driver::new_from_config(island_configuration.clone())?;
+            island.set_cancellation_token(cancellation_token.clone());
+            islands.push(island);
+        }
+
+        Ok(Archipelago {
+            configuration,
+            islands,
+            best_island: 0,
+            cancellation_token,
+        })
+    }
+
+    pub fn new_from_file(filename: &str) -> Result<Archipelago, Error> {
+        let configuration: ArchipelagoConfiguration = toml::from_str(&fs::read_to_string(filename)?)?;
+
+        Self::new(configuration)
+    }
+
+    // Returns the history of each island
+    pub fn train(&mut self, training_data: &TrainingData) -> Result<Vec<TrainingHistory>, Error> {
+        self.run_epochs(
+            |island| island.train(training_data),
+            |island| island.total_error(training_data),
+            |island, migrants| {
+                let objective = island.batch_objective(training_data);
+                island.immigrate(migrants, &objective);
+            })
+    }
+
+    pub fn train_with_fitness<F: Fitness>(&mut self, fitness: &F) -> Result<Vec<TrainingHistory>, Error> {
+        self.run_epochs(
+            |island| island.train_with_fitness(fitness),
+            |island| fitness.evaluate(&mut island.evaluator(0)),
+            |island, migrants| island.immigrate(migrants, &FitnessObjective { fitness }))
+    }
+
+    fn run_epochs<T, E, I>(&mut self, train: T, score: E, immigrate: I) -> Result<Vec<TrainingHistory>, Error>
+        where T: Fn(&mut Driver) -> Result<TrainingHistory, Error> + Sync,
+              E: Fn(&mut Driver) -> f64,
+              I: Fn(&mut Driver, Vec<Network>) {
+        let mut histories = vec![TrainingHistory::default(); self.islands.len()];
+
+        for epoch in 0..self.configuration.num_of_epochs {
+            let results: Vec<Result<TrainingHistory, Error>> = self.islands.par_iter_mut().map(&train).collect();
+
+            let mut stop = false;
+            for (history, result) in histories.iter_mut().zip(results) {
+                let result = result?;
+                stop |= matches!(result.stop_reason, Some(StopReason::DesiredErrorReached) | Some(StopReason::Cancelled));
+                history.append(result);
+            }
+
+            self.update_best_island(&score);
+            info!("Epoch: {} of {}, best island: {}, best error: {}", epoch, self.configuration.num_of_epochs, self.best_island, self.islands[self.best_island].best_error());
+
+            if stop {
+                break;
+            }
+
+            if (epoch + 1) % self.configuration.migration_interval == 0 {
+                self.migrate(&immigrate);
+            }
+        }
+
+        Ok(histories)
+    }
+
+    // The islands may use different batch sizes, so the best networks are compared with the given score
+    fn update_best_island<E: Fn(&mut Driver) -> f64>(&mut self, score: &E) {
+        let scores: Vec<f64> = self.islands.iter_mut().map(score).collect();
+
+        self.best_island = (0..scores.len()).min_by(|i1, i2| compare_errors(scores[*i1], scores[*i2])).unwrap_or(0);
+    }
+
+    // The migrants are evaluated by the receiving island before they replace its worst networks
+    fn migrate<I: Fn(&mut Driver, Vec<Network>)>(&mut self, immigrate: &I) {
+        let num_of_islands = self.islands.len();
+
+        if num_of_islands < 2 || self.configuration.num_of_migrants == 0 {
+            return
+        }
+
+        let mut incoming: Vec<Vec<_>> = (0..num_of_islands).map(|_| Vec::new()).collect();
+
+        for (source, island) in self.islands.iter().enumerate() {
+            let destinations: Vec<usize> = match self.configuration.topology {
+                MigrationTopology::Ring => vec![(source + 1) % num_of_islands],
+                MigrationTopology::FullyConnected => (0..num_of_islands).filter(|destination| *destination != source).collect(),
+            };
+
+            for destination in destinations {
+                for migrant in island.emigrants(self.configuration.num_of_migrants) {
+                    debug!("Migrate network {} from island {} to island {}", migrant.id, source, destination);
+                    incoming[destination].push(migrant);
+                }
+            }
+        }
+
+        for (island, migrants) in self.islands.iter_mut().zip(incoming) {
+            immigrate(island, migrants);
+        }
+    }
+
+    pub fn predict(&mut self, provided_input: &[f64]) -> Vec<f64> {
+        self.islands[self.best_island].predict(provided_input)
+    }
+
+    pub fn best_island(&self) -> usize {
+        self.best_island
+    }
+
+    pub fn islands(&self) -> &[Driver] {
+        &self.islands
+    }
+
+    pub fn island_mut(&mut self, index: usize) -> &mut Driver {
+        &mut self.islands[index]
+    }
+
+    // Searches all islands, the parent of a migrant is on the source island
+    pub fn lineage(&self, id: &str) -> Option<&Lineage> {
+        self.islands.iter().filter_map(|island| island.lineage(id)).next()
+    }
+
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation_token.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use lineage::{Origin};
+
+    fn configuration(topology: MigrationTopology) -> ArchipelagoConfiguration {
+        let mut islands: Vec<DriverConfiguration> = (0..3).map(|_| toml::from_str("
+            num_of_input_nodes = 2
+            num_of_output_nodes = 1
+            num_of_networks = 4
+            num_of_node_mutation = 10
+            num_of_iterations = 10
+            num_of_batch_iterations = 2
+            batch_size = 4
+            use_trained_networks = false
+            node_threshold = 0.5
+            stop_at_desired_error = false
+        ").unwrap()).collect();
+        islands[1].node_threshold = 0.2;
+        islands[2].max_network_size = 5;
+
+        ArchipelagoConfiguration {
+            topology,
+            num_of_epochs: 2,
+            migration_interval: 1,
+            num_of_migrants: 1,
+            islands,
+        }
+    }
+
+    fn xor_data() -> TrainingData {
+        TrainingData {
+            provided_input: vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]],
+            expected_output: vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]],
+        }
+    }
+
+    #[test]
+    fn islands_exchange_networks() {
+        for topology in &[MigrationTopology::Ring, MigrationTopology::FullyConnected] {
+            let mut archipelago = Archipelago::new(configuration(*topology)).unwrap();
+            let histories = archipelago.train(&xor_data()).unwrap();
+
+            assert_eq!(histories.len(), 3);
+            assert!(histories.iter().all(|history| history.iterations.len() == 4));
+
+            for island in archipelago.islands() {
+                let origins: Vec<Origin> = (0..island.num_of_networks()).map(|index| {
+                    island.lineage(&island.population_archive(None).networks[index].state.id).unwrap().origin
+                }).collect();
+                assert!(origins.contains(&Origin::Migrant));
+            }
+
+            assert_eq!(archipelago.predict(&[0.0, 1.0]).len(), 1);
+        }
+    }
+
+    #[test]
+    fn islands_must_have_same_shape() {
+        let mut configuration = configuration(MigrationTopology::Ring);
+        configuration.islands[1].num_of_input_nodes = 3;
+
+        assert!(Archipelago::new(configuration).is_err());
+    }
+
+    #[test]
+    fn invalid_island_is_an_error() {
+        let mut configuration = configuration(MigrationTopology::Ring);
+        configuration.islands[2].node_threshold = 1.5;
+
+        assert!(Archipelago::new(configuration).is_err());
+    }
+
+    #[test]
+    fn migrants_are_evaluated_by_receiving_island() {
+        let mut archipelago = Archipelago::new(configuration(MigrationTopology::Ring)).unwrap();
+        archipelago.train(&xor_data()).unwrap();
+
+        let mut migrants = archipelago.islands[0].emigrants(1);
+        migrants[0].best_error = -1.0;
+        let migrant_id = migrants[0].id.clone();
+
+        let data = xor_data();
+        let island = archipelago.island_mut(1);
+        let objective = island.batch_objective(&data);
+        island.immigrate(migrants, &objective);
+
+        let archive = island.population_archive(None);
+        let index = archive.networks.iter().position(|record| record.state.id == migrant_id).unwrap();
+        let mut evaluator = island.evaluator(index);
+        let error: f64 = data.provided_input.iter().zip(&data.expected_output)
+            .map(|(input, output)| (output[0] - evaluator.calculate(input)[0]).abs()).sum();
+        assert_eq!(archive.networks[index].state.best_error, error);
+    }
+}
@@ -5,14 +5,39 @@
 extern crate rand;
 extern crate serde;
 extern crate toml;
+extern crate serde_json;
 extern crate fnv;
 extern crate rayon;
 
 mod node;
-mod property;
+pub mod mutation;
+pub mod property;
 mod network;
-mod network_configurations;
 pub mod driver;
+pub mod registry;
+pub mod adaptation;
+pub mod analysis;
+pub mod validation;
+pub mod repair;
+pub mod population;
+pub mod lineage;
+pub mod history;
+pub mod stopping;
+pub mod fitness;
+pub mod environment;
+pub mod control_benchmarks;
+pub mod selection;
+pub mod islands;
+pub mod distributed;
+pub mod acceptance;
+pub mod gradient;
+pub mod nes;
+pub mod novelty;
+pub mod stagnation;
+pub mod hyperparameters;
+
+#[cfg(test)]
+mod test_support;
 
 /*
 TODO:
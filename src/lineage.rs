@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+// Returns a new id that is unique in this process, for example "n42" or "iris03-43"
+pub fn generate_id(prefix: &str) -> String {
+    format!("{}{}", prefix, NEXT_ID.fetch_add(1, Ordering::SeqCst))
+}
+
+// Makes sure that generated ids do not collide with ids loaded from a file
+pub fn reserve_id(id: &str) {
+    let digits: String = id.chars().rev().take_while(|c| c.is_ascii_digit()).collect::<Vec<char>>().into_iter().rev().collect();
+
+    if let Ok(number) = digits.parse::<usize>() {
+        NEXT_ID.fetch_max(number.saturating_add(1), Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Origin {
+    // Created with random nodes
+    Random,
+    // Seed from the network registry
+    Trained,
+    // Loaded from a file or given by the user
+    Loaded,
+    // Copy of another network
+    Clone,
+    // Copy of a network from another island
+    Migrant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineageEvent {
+    pub batch_iteration: usize,
+    pub description: String,
+    // Mutations per operator since the previous event of this network
+    #[serde(default)]
+    pub operator_counts: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorRecord {
+    pub batch_iteration: usize,
+    pub error: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Lineage {
+    pub id: String,
+    #[serde(default)]
+    pub parent_id: Option<String>,
+    pub origin: Origin,
+    // Name of the trained network or file name
+    #[serde(default)]
+    pub source: Option<String>,
+    pub created_iteration: usize,
+    // Mutations done by this network (not by its ancestors)
+    pub mutations_applied: u64,
+    pub mutations_accepted: u64,
+    // Applied mutations per operator, for example "DeltaBias1" or "SeparableNes"
+    #[serde(default)]
+    pub operator_counts: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub events: Vec<LineageEvent>,
+    // Best error at the end of each batch iteration
+    #[serde(default)]
+    pub error_history: Vec<ErrorRecord>,
+}
+
+impl Lineage {
+    pub fn new(id: &str, origin: Origin, created_iteration: usize) -> Lineage {
+        Lineage {
+            id: id.to_string(),
+            parent_id: None,
+            origin,
+            source: None,
+            created_iteration,
+            mutations_applied: 0,
+            mutations_accepted: 0,
+            operator_counts: BTreeMap::new(),
+            events: Vec::new(),
+            error_history: Vec::new(),
+        }
+    }
+
+    pub fn child(&self, id: &str, origin: Origin, created_iteration: usize) -> Lineage {
+        let mut lineage = Lineage::new(id, origin, created_iteration);
+        lineage.parent_id = Some(self.id.clone());
+        lineage
+    }
+
+    pub fn add_error(&mut self, batch_iteration: usize, error: f64) {
+        self.error_history.push(ErrorRecord {
+            batch_iteration,
+            error,
+        });
+    }
+
+    pub fn add_mutations(&mut self, operator: &str, num_of_applied: u64, num_of_accepted: u64) {
+        self.mutations_applied += num_of_applied;
+        self.mutations_accepted += num_of_accepted;
+        *self.operator_counts.entry(operator.to_string()).or_insert(0) += num_of_applied;
+    }
+
+    // Takes over the mutations of a copy of this network that was trained in another process
+    pub fn merge_mutations(&mut self, other: &Lineage) {
+        self.mutations_applied += other.mutations_applied;
+        self.mutations_accepted += other.mutations_accepted;
+        for (operator, count) in &other.operator_counts {
+            *self.operator_counts.entry(operator.clone()).or_insert(0) += count;
+        }
+    }
+
+    pub fn add_event(&mut self, batch_iteration: usize, description: String) {
+        let operator_counts = self.operator_counts_since_last_event();
+
+        self.events.push(LineageEvent {
+            batch_iteration,
+            description,
+            operator_counts,
+        });
+    }
+
+    fn operator_counts_since_last_event(&self) -> BTreeMap<String, u64> {
+        let mut counts = self.operator_counts.clone();
+
+        for event in &self.events {
+            for (operator, count) in &event.operator_counts {
+                if let Some(total) = counts.get_mut(operator) {
+                    *total = total.saturating_sub(*count);
+                }
+            }
+        }
+
+        counts.retain(|_, count| *count > 0);
+        counts
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generated_ids_are_unique() {
+        let id1 = generate_id("n");
+        let id2 = generate_id("n");
+
+        assert_ne!(id1, id2);
+    }
+
+    #[test]
+    fn events_count_the_operators_since_the_previous_event() {
+        let mut lineage = Lineage::new("n1", Origin::Random, 0);
+        lineage.add_mutations("DeltaBias1", 3, 1);
+        lineage.add_mutations("SwapNodes", 1, 0);
+        lineage.add_event(0, "added node".to_string());
+        lineage.add_mutations("DeltaBias1", 2, 2);
+        lineage.add_event(1, "fine-tuned".to_string());
+
+        assert_eq!(lineage.mutations_applied, 6);
+        assert_eq!(lineage.mutations_accepted, 3);
+        assert_eq!(lineage.operator_counts.get("DeltaBias1"), Some(&5));
+
+        let counts = |counts: &[(&str, u64)]| counts.iter().map(|(operator, count)| (operator.to_string(), *count)).collect::<BTreeMap<String, u64>>();
+        assert_eq!(lineage.events[0].operator_counts, counts(&[("DeltaBias1", 3), ("SwapNodes", 1)]));
+        assert_eq!(lineage.events[1].operator_counts, counts(&[("DeltaBias1", 2)]));
+    }
+
+    #[test]
+    fn reserved_ids_are_skipped() {
+        reserve_id("n1000000");
+        let id: usize = generate_id("").parse().unwrap();
+
+        assert!(id > 1000000);
+    }
+}
@@ -0,0 +1,572 @@
+use std::fmt;
+use std::collections::BTreeMap;
+
+use rand::{Rng};
+use failure::Fail;
+
+use driver::{DriverConfiguration};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MutateNodeOperation {
+    SwapConnections,
+    AddConnection,
+    RemoveConnection,
+    RandomConnectionOne,
+    RandomConnectionAll,
+    DeltaBias1,
+    DeltaBias2,
+    DeltaBias3,
+    DeltaBias4,
+    RandomBias,
+    DeltaWeightOne1,
+    DeltaWeightOne2,
+    DeltaWeightOne3,
+    DeltaWeightOne4,
+    RandomWeightOne,
+    DeltaWeightAll1,
+    DeltaWeightAll2,
+    DeltaWeightAll3,
+    DeltaWeightAll4,
+    RandomWeightAll,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MutatePropertyOperation {
+    SwapNodes,
+    SwapOutput,
+    RandomOutputOne,
+    RandomOutputAll,
+    SplitConnection,
+    RemoveNode,
+    DuplicateNode,
+}
+
+pub const NODE_OPERATIONS : [MutateNodeOperation; 20] = [
+    MutateNodeOperation::SwapConnections,
+    MutateNodeOperation::AddConnection,
+    MutateNodeOperation::RemoveConnection,
+    MutateNodeOperation::RandomConnectionOne,
+    MutateNodeOperation::RandomConnectionAll,
+    MutateNodeOperation::DeltaBias1,
+    MutateNodeOperation::DeltaBias2,
+    MutateNodeOperation::DeltaBias3,
+    MutateNodeOperation::DeltaBias4,
+    MutateNodeOperation::RandomBias,
+    MutateNodeOperation::DeltaWeightOne1,
+    MutateNodeOperation::DeltaWeightOne2,
+    MutateNodeOperation::DeltaWeightOne3,
+    MutateNodeOperation::DeltaWeightOne4,
+    MutateNodeOperation::RandomWeightOne,
+    MutateNodeOperation::DeltaWeightAll1,
+    MutateNodeOperation::DeltaWeightAll2,
+    MutateNodeOperation::DeltaWeightAll3,
+    MutateNodeOperation::DeltaWeightAll4,
+    MutateNodeOperation::RandomWeightAll,
+];
+
+pub const PROPERTY_OPERATIONS : [MutatePropertyOperation; 7] = [
+    MutatePropertyOperation::SwapNodes,
+    MutatePropertyOperation::SwapOutput,
+    MutatePropertyOperation::RandomOutputOne,
+    MutatePropertyOperation::RandomOutputAll,
+    MutatePropertyOperation::SplitConnection,
+    MutatePropertyOperation::RemoveNode,
+    MutatePropertyOperation::DuplicateNode,
+];
+
+pub const NUM_OF_OPERATIONS : usize = NODE_OPERATIONS.len() + PROPERTY_OPERATIONS.len();
+
+// Structural operations change the size of the network, so they are used less often by default
+const STRUCTURAL_OPERATION_WEIGHT : f64 = 0.1;
+
+// Relative probability of an operation if the configuration does not set one
+trait DefaultWeight: fmt::Debug {
+    fn default_weight(&self) -> f64;
+}
+
+impl DefaultWeight for MutateNodeOperation {
+    fn default_weight(&self) -> f64 {
+        1.0
+    }
+}
+
+impl DefaultWeight for MutatePropertyOperation {
+    fn default_weight(&self) -> f64 {
+        match *self {
+            MutatePropertyOperation::SplitConnection |
+            MutatePropertyOperation::RemoveNode |
+            MutatePropertyOperation::DuplicateNode => STRUCTURAL_OPERATION_WEIGHT,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum MutationOperation {
+    Node(MutateNodeOperation),
+    Property(MutatePropertyOperation),
+}
+
+impl MutationOperation {
+    pub fn all() -> Vec<MutationOperation> {
+        NODE_OPERATIONS.iter().map(|operation| MutationOperation::Node(*operation)).chain(
+            PROPERTY_OPERATIONS.iter().map(|operation| MutationOperation::Property(*operation))).collect()
+    }
+
+    // Node operations first, then property operations
+    pub fn index(&self) -> usize {
+        match *self {
+            MutationOperation::Node(operation) => NODE_OPERATIONS.iter().position(|o| *o == operation).unwrap(),
+            MutationOperation::Property(operation) => NODE_OPERATIONS.len() + PROPERTY_OPERATIONS.iter().position(|o| *o == operation).unwrap(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match *self {
+            MutationOperation::Node(operation) => format!("{:?}", operation),
+            MutationOperation::Property(operation) => format!("{:?}", operation),
+        }
+    }
+}
+
+// How often each mutation operation was applied and how often it led to an accepted improvement
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperatorStatistics {
+    pub attempts: Vec<u64>,
+    pub successes: Vec<u64>,
+}
+
+impl Default for OperatorStatistics {
+    fn default() -> OperatorStatistics {
+        OperatorStatistics {
+            attempts: vec![0; NUM_OF_OPERATIONS],
+            successes: vec![0; NUM_OF_OPERATIONS],
+        }
+    }
+}
+
+impl OperatorStatistics {
+    pub fn record(&mut self, operation: MutationOperation, success: bool) {
+        let index = operation.index();
+        self.attempts[index] += 1;
+        if success {
+            self.successes[index] += 1;
+        }
+    }
+
+    pub fn attempts(&self, operation: MutationOperation) -> u64 {
+        self.attempts[operation.index()]
+    }
+
+    pub fn successes(&self, operation: MutationOperation) -> u64 {
+        self.successes[operation.index()]
+    }
+
+    pub fn success_rate(&self, operation: MutationOperation) -> f64 {
+        let attempts = self.attempts(operation);
+
+        if attempts == 0 {
+            0.0
+        } else {
+            (self.successes(operation) as f64) / (attempts as f64)
+        }
+    }
+
+    pub fn merge(&mut self, other: &OperatorStatistics) {
+        for index in 0..NUM_OF_OPERATIONS {
+            self.attempts[index] += other.attempts[index];
+            self.successes[index] += other.successes[index];
+        }
+    }
+
+    // Operations with the most successes first
+    pub fn ranking(&self) -> Vec<(MutationOperation, u64, u64, f64)> {
+        let mut result: Vec<(MutationOperation, u64, u64, f64)> = MutationOperation::all().into_iter().map(|operation| {
+            (operation, self.attempts(operation), self.successes(operation), self.success_rate(operation))
+        }).collect();
+
+        result.sort_by(|r1, r2| r2.2.cmp(&r1.2).then(r2.1.cmp(&r1.1)));
+        result
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigurationError {
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid configuration: {}", self.errors.join("; "))
+    }
+}
+
+impl Fail for ConfigurationError {}
+
+// Mutation parameters taken from the DriverConfiguration
+#[derive(Debug, Clone, PartialEq)]
+pub struct MutationSettings {
+    // Relative probabilities, same order as NODE_OPERATIONS / PROPERTY_OPERATIONS
+    pub node_weights: Vec<f64>,
+    pub property_weights: Vec<f64>,
+    // Step sizes for the Delta*1 .. Delta*4 operations
+    pub delta_limits: [f64; 4],
+    // New weights / biases are drawn from -range..range
+    pub weight_range: f64,
+    pub bias_range: f64,
+    // Upper limit for the structural operations
+    pub max_network_size: usize,
+    // Adaptive operator selection (probability matching)
+    pub adaptive_operator_selection: bool,
+    pub operator_adaptation_rate: f64,
+    pub min_operator_probability: f64,
+    pub node_quality: Vec<f64>,
+    pub property_quality: Vec<f64>,
+    // 1/5th success rule, scales all delta limits
+    pub step_size_adaptation: bool,
+    pub step_size_window: usize,
+    pub step_scale: f64,
+    window_attempts: usize,
+    window_successes: usize,
+}
+
+// The part of MutationSettings that changes during training, saved with the network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MutationState {
+    pub node_weights: Vec<f64>,
+    pub property_weights: Vec<f64>,
+    pub node_quality: Vec<f64>,
+    pub property_quality: Vec<f64>,
+    pub step_scale: f64,
+    pub window_attempts: usize,
+    pub window_successes: usize,
+}
+
+const STEP_SCALE_FACTOR : f64 = 0.82;
+pub(crate) const MIN_STEP_SCALE : f64 = 1.0e-6;
+pub(crate) const MAX_STEP_SCALE : f64 = 1.0e6;
+
+fn operation_weights<T: DefaultWeight>(operations: &[T], weights: &BTreeMap<String, f64>) -> Vec<f64> {
+    operations.iter().map(|operation| {
+        weights.get(&format!("{:?}", operation)).cloned().unwrap_or_else(|| operation.default_weight())
+    }).collect()
+}
+
+fn check_operation_weights<T: DefaultWeight>(name: &str, operations: &[T], weights: &BTreeMap<String, f64>, errors: &mut Vec<String>) {
+    let names: Vec<String> = operations.iter().map(|operation| format!("{:?}", operation)).collect();
+
+    for (operation, weight) in weights {
+        if !names.contains(operation) {
+            errors.push(format!("{}: unknown operation '{}'", name, operation));
+        } else if !weight.is_finite() || *weight < 0.0 {
+            errors.push(format!("{}: weight for '{}' must be finite and >= 0, given: {}", name, operation, weight));
+        }
+    }
+
+    if operation_weights(operations, weights).iter().all(|weight| *weight <= 0.0) {
+        errors.push(format!("{}: at least one operation must have a weight > 0", name));
+    }
+}
+
+fn normalized(weights: &[f64]) -> Vec<f64> {
+    let total: f64 = weights.iter().sum();
+    weights.iter().map(|weight| weight / total).collect()
+}
+
+// Probability matching: every enabled operation keeps at least min_probability,
+// the rest is distributed according to the estimated quality.
+fn match_probabilities(weights: &mut [f64], quality: &[f64], min_probability: f64) {
+    let num_of_enabled = weights.iter().filter(|weight| **weight > 0.0).count() as f64;
+    let total_quality: f64 = quality.iter().zip(weights.iter()).filter(|(_, weight)| **weight > 0.0).map(|(q, _)| q).sum();
+
+    if total_quality <= 0.0 {
+        return
+    }
+
+    for (weight, q) in weights.iter_mut().zip(quality) {
+        if *weight > 0.0 {
+            *weight = min_probability + (1.0 - num_of_enabled * min_probability) * q / total_quality;
+        }
+    }
+}
+
+fn choose_weighted<T: Rng>(rng: &mut T, weights: &[f64]) -> usize {
+    let total: f64 = weights.iter().sum();
+    let mut value = rng.gen_range::<f64>(0.0, total);
+
+    for (index, weight) in weights.iter().enumerate() {
+        if value < *weight {
+            return index
+        }
+        value -= weight;
+    }
+
+    // Rounding errors: take the last operation that can be chosen at all
+    weights.iter().rposition(|weight| *weight > 0.0).unwrap()
+}
+
+impl MutationSettings {
+    pub fn new(configuration: &DriverConfiguration) -> MutationSettings {
+        let node_weights = operation_weights(&NODE_OPERATIONS, &configuration.node_operation_weights);
+        let property_weights = operation_weights(&PROPERTY_OPERATIONS, &configuration.property_operation_weights);
+        let node_quality = normalized(&node_weights);
+        let property_quality = normalized(&property_weights);
+
+        MutationSettings {
+            node_weights,
+            property_weights,
+            delta_limits: configuration.delta_limits,
+            weight_range: configuration.weight_range,
+            bias_range: configuration.bias_range,
+            max_network_size: configuration.max_network_size,
+            adaptive_operator_selection: configuration.adaptive_operator_selection,
+            operator_adaptation_rate: configuration.operator_adaptation_rate,
+            min_operator_probability: configuration.min_operator_probability,
+            node_quality,
+            property_quality,
+            step_size_adaptation: configuration.step_size_adaptation,
+            step_size_window: configuration.step_size_window,
+            step_scale: 1.0,
+            window_attempts: 0,
+            window_successes: 0,
+        }
+    }
+
+    // Called after each mutation with the information if the mutation improved the network
+    pub fn update(&mut self, operation: MutationOperation, success: bool) {
+        if self.adaptive_operator_selection {
+            let reward = if success { 1.0 } else { 0.0 };
+            let rate = self.operator_adaptation_rate;
+
+            match operation {
+                MutationOperation::Node(_) => {
+                    let index = operation.index();
+                    self.node_quality[index] += rate * (reward - self.node_quality[index]);
+                    match_probabilities(&mut self.node_weights, &self.node_quality, self.min_operator_probability);
+                }
+                MutationOperation::Property(_) => {
+                    let index = operation.index() - NODE_OPERATIONS.len();
+                    self.property_quality[index] += rate * (reward - self.property_quality[index]);
+                    match_probabilities(&mut self.property_weights, &self.property_quality, self.min_operator_probability);
+                }
+            }
+        }
+
+        if self.step_size_adaptation {
+            self.window_attempts += 1;
+            if success {
+                self.window_successes += 1;
+            }
+
+            if self.window_attempts >= self.step_size_window {
+                let success_rate = (self.window_successes as f64) / (self.window_attempts as f64);
+
+                if success_rate > 0.2 {
+                    self.step_scale /= STEP_SCALE_FACTOR;
+                } else if success_rate < 0.2 {
+                    self.step_scale *= STEP_SCALE_FACTOR;
+                }
+
+                self.step_scale = self.step_scale.clamp(MIN_STEP_SCALE, MAX_STEP_SCALE);
+                self.window_attempts = 0;
+                self.window_successes = 0;
+            }
+        }
+    }
+
+    pub fn state(&self) -> MutationState {
+        MutationState {
+            node_weights: self.node_weights.clone(),
+            property_weights: self.property_weights.clone(),
+            node_quality: self.node_quality.clone(),
+            property_quality: self.property_quality.clone(),
+            step_scale: self.step_scale,
+            window_attempts: self.window_attempts,
+            window_successes: self.window_successes,
+        }
+    }
+
+    // The operation weights are only taken over with adaptive_operator_selection,
+    // otherwise the weights of the configuration apply
+    pub fn set_state(&mut self, state: MutationState) {
+        let same_operations = state.node_weights.len() == NODE_OPERATIONS.len() && state.node_quality.len() == NODE_OPERATIONS.len() &&
+            state.property_weights.len() == PROPERTY_OPERATIONS.len() && state.property_quality.len() == PROPERTY_OPERATIONS.len();
+
+        if self.adaptive_operator_selection && same_operations {
+            self.node_weights = state.node_weights;
+            self.property_weights = state.property_weights;
+            self.node_quality = state.node_quality;
+            self.property_quality = state.property_quality;
+        }
+
+        self.step_scale = state.step_scale.clamp(MIN_STEP_SCALE, MAX_STEP_SCALE);
+        self.window_attempts = state.window_attempts;
+        self.window_successes = state.window_successes;
+    }
+
+    // Bigger steps to leave a local optimum, the 1/5th rule shrinks them again if step_size_adaptation is on
+    pub fn boost_step_scale(&mut self, factor: f64) {
+        self.step_scale = (self.step_scale * factor).clamp(MIN_STEP_SCALE, MAX_STEP_SCALE);
+    }
+
+    // Current selection probability for each operation, same order as MutationOperation::all()
+    pub fn operation_probabilities(&self) -> Vec<f64> {
+        normalized(&self.node_weights).into_iter().chain(normalized(&self.property_weights)).collect()
+    }
+
+    pub fn validate(configuration: &DriverConfiguration) -> Result<(), ConfigurationError> {
+        let mut errors = Vec::new();
+
+        check_operation_weights("node_operation_weights", &NODE_OPERATIONS, &configuration.node_operation_weights, &mut errors);
+        check_operation_weights("property_operation_weights", &PROPERTY_OPERATIONS, &configuration.property_operation_weights, &mut errors);
+
+        for limit in &configuration.delta_limits {
+            if !limit.is_finite() || *limit <= 0.0 {
+                errors.push(format!("delta_limits: all limits must be finite and > 0, given: {}", limit));
+            }
+        }
+
+        if !configuration.weight_range.is_finite() || configuration.weight_range <= 0.0 {
+            errors.push(format!("weight_range must be finite and > 0, given: {}", configuration.weight_range));
+        }
+
+        if !configuration.bias_range.is_finite() || configuration.bias_range <= 0.0 {
+            errors.push(format!("bias_range must be finite and > 0, given: {}", configuration.bias_range));
+        }
+
+        if !(configuration.operator_adaptation_rate > 0.0 && configuration.operator_adaptation_rate <= 1.0) {
+            errors.push(format!("operator_adaptation_rate must be in (0, 1], given: {}", configuration.operator_adaptation_rate));
+        }
+
+        if !(configuration.min_operator_probability >= 0.0 && configuration.min_operator_probability * (NODE_OPERATIONS.len() as f64) < 1.0) {
+            errors.push(format!("min_operator_probability must be in [0, {}), given: {}", 1.0 / (NODE_OPERATIONS.len() as f64), configuration.min_operator_probability));
+        }
+
+        if configuration.step_size_window == 0 {
+            errors.push("step_size_window must be > 0".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigurationError { errors })
+        }
+    }
+
+    pub fn choose_node_operation<T: Rng>(&self, rng: &mut T) -> MutateNodeOperation {
+        NODE_OPERATIONS[choose_weighted(rng, &self.node_weights)]
+    }
+
+    pub fn choose_property_operation<T: Rng>(&self, rng: &mut T) -> MutatePropertyOperation {
+        PROPERTY_OPERATIONS[choose_weighted(rng, &self.property_weights)]
+    }
+
+    pub fn random_weight<T: Rng>(&self, rng: &mut T) -> f64 {
+        rng.gen_range::<f64>(-self.weight_range, self.weight_range)
+    }
+
+    pub fn random_bias<T: Rng>(&self, rng: &mut T) -> f64 {
+        rng.gen_range::<f64>(-self.bias_range, self.bias_range)
+    }
+
+    pub fn random_delta<T: Rng>(&self, rng: &mut T, level: usize) -> f64 {
+        let limit = self.delta_limits[level] * self.step_scale;
+        rng.gen_range::<f64>(-limit, limit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::{XorShiftRng, SeedableRng};
+    use toml;
+
+    #[test]
+    fn zero_weight_operations_are_never_chosen() {
+        let mut configuration: DriverConfiguration = toml::from_str("").unwrap();
+        configuration.property_operation_weights.insert("SwapNodes".to_string(), 0.0);
+        configuration.property_operation_weights.insert("SwapOutput".to_string(), 0.0);
+        configuration.property_operation_weights.insert("RandomOutputAll".to_string(), 0.0);
+        configuration.property_operation_weights.insert("SplitConnection".to_string(), 0.0);
+        configuration.property_operation_weights.insert("RemoveNode".to_string(), 0.0);
+        configuration.property_operation_weights.insert("DuplicateNode".to_string(), 0.0);
+
+        let settings = MutationSettings::new(&configuration);
+        let mut rng = XorShiftRng::from_seed([2; 16]);
+
+        for _ in 0..1000 {
+            assert_eq!(settings.choose_property_operation(&mut rng), MutatePropertyOperation::RandomOutputOne);
+        }
+    }
+
+    #[test]
+    fn structural_operations_have_lower_default_weight() {
+        let configuration: DriverConfiguration = toml::from_str("").unwrap();
+        let settings = MutationSettings::new(&configuration);
+        let structural = [MutatePropertyOperation::SplitConnection, MutatePropertyOperation::RemoveNode, MutatePropertyOperation::DuplicateNode];
+
+        for (operation, weight) in PROPERTY_OPERATIONS.iter().zip(&settings.property_weights) {
+            let expected = if structural.contains(operation) { STRUCTURAL_OPERATION_WEIGHT } else { 1.0 };
+            assert_eq!(*weight, expected, "{:?}", operation);
+        }
+
+        assert_eq!(MutationOperation::all().len(), NUM_OF_OPERATIONS);
+    }
+
+    #[test]
+    fn successful_operations_get_more_likely() {
+        let mut configuration: DriverConfiguration = toml::from_str("").unwrap();
+        configuration.adaptive_operator_selection = true;
+
+        let mut settings = MutationSettings::new(&configuration);
+        let good = MutationOperation::Node(MutateNodeOperation::DeltaBias2);
+        let bad = MutationOperation::Node(MutateNodeOperation::RandomBias);
+
+        for _ in 0..100 {
+            settings.update(good, true);
+            settings.update(bad, false);
+        }
+
+        let probabilities = settings.operation_probabilities();
+        assert!(probabilities[good.index()] > 0.3);
+        assert!(probabilities[bad.index()] < 0.02);
+        assert!(probabilities[bad.index()] >= configuration.min_operator_probability - 1.0e-12);
+        assert!((probabilities.iter().take(NODE_OPERATIONS.len()).sum::<f64>() - 1.0).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn step_size_follows_one_fifth_rule() {
+        let mut configuration: DriverConfiguration = toml::from_str("").unwrap();
+        configuration.step_size_adaptation = true;
+        configuration.step_size_window = 10;
+
+        let mut settings = MutationSettings::new(&configuration);
+        let operation = MutationOperation::Node(MutateNodeOperation::DeltaBias1);
+
+        for _ in 0..10 {
+            settings.update(operation, false);
+        }
+        assert!(settings.step_scale < 1.0);
+
+        for _ in 0..20 {
+            settings.update(operation, true);
+        }
+        assert!(settings.step_scale > 1.0);
+    }
+
+    #[test]
+    fn bad_weights_are_rejected() {
+        let configuration: DriverConfiguration = toml::from_str("
+            delta_limits = [1.0, 0.0, 0.1, 0.01]
+
+            [node_operation_weights]
+            DeltaBias1 = -1.0
+            NoSuchOperation = 1.0
+        ").unwrap();
+
+        let errors = MutationSettings::validate(&configuration).unwrap_err().errors;
+
+        assert_eq!(errors.len(), 3);
+    }
+}
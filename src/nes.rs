@@ -0,0 +1,156 @@
+// Separable natural evolution strategy (SNES) over the flattened weights and biases of a fixed structure.
+// Each parameter has its own step size sigma, which adapts to the scale of that parameter.
+// Used by Network::optimize() instead of the node mutations, see DriverConfiguration::weight_optimizer.
+
+use std::cmp::Ordering;
+
+use rand::{Rng};
+use rand::distributions::{StandardNormal};
+
+use driver::{DriverConfiguration};
+use mutation::{ConfigurationError};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeightOptimizer {
+    // Random node and property mutations, the structure evolves, too
+    Mutation,
+    // Only the weights and biases change
+    SeparableNes,
+}
+
+pub fn validate(configuration: &DriverConfiguration) -> Result<(), ConfigurationError> {
+    let mut errors = Vec::new();
+
+    if !(configuration.weight_optimizer_share >= 0.0 && configuration.weight_optimizer_share <= 1.0) {
+        errors.push(format!("weight_optimizer_share must be in [0, 1], given: {}", configuration.weight_optimizer_share));
+    }
+
+    if !configuration.initial_sigma.is_finite() || configuration.initial_sigma <= 0.0 {
+        errors.push(format!("initial_sigma must be finite and > 0, given: {}", configuration.initial_sigma));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigurationError { errors })
+    }
+}
+
+// Rank based utilities, best sample first, they sum up to zero
+fn utilities(population_size: usize) -> Vec<f64> {
+    let weights: Vec<f64> = (1..=population_size).map(|rank| {
+        ((((population_size as f64) / 2.0) + 1.0).ln() - (rank as f64).ln()).max(0.0)
+    }).collect();
+    let sum: f64 = weights.iter().sum();
+
+    weights.iter().map(|weight| (weight / sum) - (1.0 / (population_size as f64))).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeparableNes {
+    sigma: Vec<f64>,
+}
+
+impl SeparableNes {
+    pub fn new(num_of_parameters: usize, initial_sigma: f64) -> SeparableNes {
+        SeparableNes {
+            sigma: vec![initial_sigma; num_of_parameters],
+        }
+    }
+
+    // Continues with the step sizes of an earlier run
+    pub fn with_sigma(sigma: Vec<f64>) -> SeparableNes {
+        SeparableNes {
+            sigma,
+        }
+    }
+
+    pub fn num_of_parameters(&self) -> usize {
+        self.sigma.len()
+    }
+
+    pub fn sigma(&self) -> &[f64] {
+        &self.sigma
+    }
+
+    // Default population size of SNES
+    pub fn population_size(&self) -> usize {
+        4 + (3.0 * (self.sigma.len().max(1) as f64).ln()).floor() as usize
+    }
+
+    // Searches around mean with at most num_of_evaluations calls of error().
+    // Returns the best sample and its error, None if no sample was evaluated.
+    pub fn run<R: Rng, F: FnMut(&[f64]) -> f64>(&mut self, mean: &[f64], num_of_evaluations: usize, rng: &mut R, mut error: F) -> Option<(Vec<f64>, f64)> {
+        assert_eq!(mean.len(), self.sigma.len(), "Number of parameters changed");
+
+        let num_of_parameters = mean.len();
+        let population_size = self.population_size().min(num_of_evaluations.max(2));
+        let num_of_generations = num_of_evaluations / population_size;
+        let utilities = utilities(population_size);
+        let sigma_learning_rate = (3.0 + (num_of_parameters.max(1) as f64).ln()) / (5.0 * (num_of_parameters.max(1) as f64).sqrt());
+
+        let mut mean = mean.to_vec();
+        let mut best: Option<(Vec<f64>, f64)> = None;
+
+        for _ in 0..num_of_generations {
+            let mut samples: Vec<(Vec<f64>, f64)> = (0..population_size).map(|_| {
+                let noise: Vec<f64> = (0..num_of_parameters).map(|_| rng.sample(StandardNormal)).collect();
+                let parameters: Vec<f64> = mean.iter().zip(&self.sigma).zip(&noise).map(|((mean, sigma), noise)| mean + (sigma * noise)).collect();
+                let sample_error = error(&parameters);
+
+                if best.as_ref().map_or(true, |best| sample_error < best.1) {
+                    best = Some((parameters, sample_error));
+                }
+
+                (noise, sample_error)
+            }).collect();
+
+            samples.sort_by(|s1, s2| s1.1.partial_cmp(&s2.1).unwrap_or(Ordering::Equal));
+
+            for (i, (mean, sigma)) in mean.iter_mut().zip(&mut self.sigma).enumerate() {
+                let mean_gradient: f64 = samples.iter().zip(&utilities).map(|(sample, utility)| utility * sample.0[i]).sum();
+                let sigma_gradient: f64 = samples.iter().zip(&utilities).map(|(sample, utility)| utility * ((sample.0[i] * sample.0[i]) - 1.0)).sum();
+
+                *mean += *sigma * mean_gradient;
+                *sigma *= (0.5 * sigma_learning_rate * sigma_gradient).exp();
+            }
+        }
+
+        best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::{XorShiftRng, SeedableRng};
+
+    #[test]
+    fn utilities_prefer_best_samples() {
+        let utilities = utilities(8);
+
+        assert!(utilities.iter().sum::<f64>().abs() < 1e-12);
+        assert!(utilities.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn finds_minimum_of_scaled_sphere() {
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+        let scales = [1.0, 10.0, 100.0];
+        let mut nes = SeparableNes::new(3, 0.5);
+        let mut mean = vec![1.0, 1.0, 1.0];
+
+        for _ in 0..20 {
+            let (best, _) = nes.run(&mean, 100, &mut rng, |parameters| {
+                parameters.iter().zip(&scales).map(|(parameter, scale)| scale * parameter * parameter).sum()
+            }).unwrap();
+            mean = best;
+        }
+
+        assert!(mean.iter().all(|parameter| parameter.abs() < 0.05), "{:?}", mean);
+        // The step size of the steepest direction shrinks the most
+        assert!(nes.sigma()[2] < nes.sigma()[0]);
+    }
+}
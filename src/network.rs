@@ -1,10 +1,44 @@
 use std::f64;
 
-use rand::{self, XorShiftRng, FromEntropy};
+use rand::{self, Rng, XorShiftRng, FromEntropy};
 
 use driver::{DriverConfiguration, TrainingData};
 use property::{Property};
 use node::{Node};
+use analysis::{GraphAnalysis};
+use validation::{PropertyViolation};
+use repair::{RepairReport};
+use population::{NetworkRecord, NetworkState};
+use lineage::{self, Lineage, Origin};
+use mutation::{MutationSettings, MutationOperation, MutationState, OperatorStatistics};
+use fitness::{Objective};
+use acceptance::{AcceptanceState};
+use gradient;
+use nes::{SeparableNes, WeightOptimizer};
+use hyperparameters::{Hyperparameters};
+
+// NaN or inf outputs are scored as worst possible error
+pub fn worst_if_not_finite(error: f64) -> f64 {
+    if error.is_finite() {
+        error
+    } else {
+        f64::MAX
+    }
+}
+
+fn choose_weight_optimizer<R: Rng>(configuration: &DriverConfiguration, rng: &mut R) -> WeightOptimizer {
+    if rng.gen::<f64>() < configuration.weight_optimizer_share {
+        configuration.weight_optimizer
+    } else {
+        WeightOptimizer::Mutation
+    }
+}
+
+// A network keeps its weight optimizer if choose_weight_optimizer() could have picked it
+fn can_choose_weight_optimizer(configuration: &DriverConfiguration, weight_optimizer: WeightOptimizer) -> bool {
+    (weight_optimizer == configuration.weight_optimizer && configuration.weight_optimizer_share > 0.0) ||
+        (weight_optimizer == WeightOptimizer::Mutation && configuration.weight_optimizer_share < 1.0)
+}
 
 #[derive(Debug, Clone)]
 pub struct Network {
@@ -12,6 +46,14 @@ pub struct Network {
     property: Property,
     undo_property: Property,
     nodes_output_values: Vec<f64>,
+    mutation_settings: MutationSettings,
+    operator_statistics: OperatorStatistics,
+    acceptance: AcceptanceState,
+    weight_optimizer: WeightOptimizer,
+    // Step sizes of the evolution strategy, created on first use
+    nes: Option<SeparableNes>,
+    hyperparameters: Hyperparameters,
+    lineage: Lineage,
     rng: XorShiftRng,
     pub best_error: f64,
     pub id: String,
@@ -22,9 +64,10 @@ impl Network {
     pub fn new(configuration: DriverConfiguration) -> Network {
         let mut nodes = Vec::with_capacity(configuration.initial_network_size);
         let mut rng = rand::thread_rng();
+        let mutation_settings = MutationSettings::new(&configuration);
 
         for _ in 0..configuration.initial_network_size {
-            nodes.push(Node::new_simple(&mut rng));
+            nodes.push(Node::new_simple(&mut rng, &mutation_settings));
         }
 
         let output_indices = vec![0; configuration.num_of_output_nodes];
@@ -34,7 +77,9 @@ impl Network {
             output_indices,
         };
 
-        Self::new_with_property(configuration, property, "untrained")
+        let mut network = Self::new_with_property(configuration, property, &lineage::generate_id("n"));
+        network.lineage.origin = Origin::Random;
+        network
     }
 
     pub fn new_with_property(configuration: DriverConfiguration, property: Property, id: &str) -> Network {
@@ -44,33 +89,67 @@ impl Network {
         };
 
         let nodes_output_values = vec![0.0; configuration.num_of_input_nodes + property.nodes.len()];
+        let mutation_settings = MutationSettings::new(&configuration);
+        let acceptance = AcceptanceState::new(&configuration);
+        let mut rng = XorShiftRng::from_entropy();
+        let weight_optimizer = choose_weight_optimizer(&configuration, &mut rng);
+        let hyperparameters = Hyperparameters::new(&configuration);
 
         Network {
             configuration,
             property,
             undo_property,
             nodes_output_values,
-            rng: XorShiftRng::from_entropy(),
+            mutation_settings,
+            operator_statistics: OperatorStatistics::default(),
+            acceptance,
+            weight_optimizer,
+            nes: None,
+            hyperparameters,
+            lineage: Lineage::new(id, Origin::Loaded, 0),
+            rng,
             best_error: f64::MAX,
             id: id.to_string(),
             first_place_counter: 0,
         }
     }
 
+    // The adapted state of the network is kept as far as the new configuration allows it, see from_record()
     pub fn set_configuration(&mut self, configuration: DriverConfiguration) {
+        let mutation_state = self.mutation_settings.state();
+        let policy = self.acceptance.policy();
+        let temperature = self.acceptance.temperature();
+        let hyperparameters = self.hyperparameters.clone();
+
+        self.mutation_settings = MutationSettings::new(&configuration);
+        self.mutation_settings.set_state(mutation_state);
+        self.acceptance = AcceptanceState::new(&configuration);
+        // A temperature of another policy has another meaning
+        if let (Some(temperature), true) = (temperature, self.acceptance.policy() == policy) {
+            self.acceptance.set_temperature(temperature);
+        }
+        if !can_choose_weight_optimizer(&configuration, self.weight_optimizer) {
+            self.weight_optimizer = choose_weight_optimizer(&configuration, &mut self.rng);
+            self.nes = None;
+        }
+        self.hyperparameters = Hyperparameters::new(&configuration);
         self.configuration = configuration;
+        self.restore_hyperparameters(Some(hyperparameters));
+    }
+
+    pub fn configuration(&self) -> &DriverConfiguration {
+        &self.configuration
     }
 
-    fn reset_values(&mut self) {
+    pub(crate) fn reset_values(&mut self) {
         for value in &mut self.nodes_output_values {
             *value = 0.0;
         }
     }
 
-    fn calculate_once(&mut self, provided_input: &[f64]) {
-        for i in 0..self.configuration.num_of_input_nodes {
-            self.nodes_output_values[i] = provided_input[i];
-        }
+    pub(crate) fn calculate_once(&mut self, provided_input: &[f64]) {
+        let num_of_input_nodes = self.configuration.num_of_input_nodes;
+        self.nodes_output_values[..num_of_input_nodes].copy_from_slice(&provided_input[..num_of_input_nodes]);
 
         for i in 0..self.property.nodes.len() {
             self.nodes_output_values[self.configuration.num_of_input_nodes + i] = self.property.nodes[i].calculate(&self.nodes_output_values);
@@ -80,7 +159,7 @@ impl Network {
     pub fn calculate(&mut self, provided_input: &[f64]) {
         self.reset_values();
         for _ in 0..self.configuration.num_of_cycles {
-            self.calculate_once(&provided_input);
+            self.calculate_once(provided_input);
         }
     }
 
@@ -90,80 +169,416 @@ impl Network {
         })
     }
 
-    fn calculate_batch_and_error(&mut self, indices: &[usize], training_data: &TrainingData) -> f64 {
-        indices.iter().take(self.configuration.batch_size).fold(0.0, |error, index| {
+    pub fn calculate_batch_and_error(&mut self, indices: &[usize], training_data: &TrainingData) -> f64 {
+        let error = indices.iter().take(self.configuration.batch_size).fold(0.0, |error, index| {
             self.calculate(&training_data.provided_input[*index]);
             error + self.calculate_error(&training_data.expected_output[*index])
-        })
+        });
+
+        worst_if_not_finite(error)
+    }
+
+    // Error over all entries, not just the current batch
+    pub fn calculate_total_error(&mut self, data: &TrainingData) -> f64 {
+        let error = data.provided_input.iter().zip(&data.expected_output).fold(0.0, |error, (provided_input, expected_output)| {
+            self.calculate(provided_input);
+            error + self.calculate_error(expected_output)
+        });
+
+        worst_if_not_finite(error)
+    }
+
+    // Outputs for all probe inputs one after another, used by novelty search
+    pub fn behaviour(&mut self, probe_inputs: &[Vec<f64>]) -> Vec<f64> {
+        let mut result = Vec::with_capacity(probe_inputs.len() * self.num_of_outputs());
+
+        for provided_input in probe_inputs {
+            self.calculate(provided_input);
+            result.extend(self.get_output());
+        }
+
+        result
     }
 
     pub fn get_output(&self) -> Vec<f64> {
         self.property.output_indices.iter().map(|index| self.nodes_output_values[*index]).collect()
     }
 
-    pub fn maybe_add_node(&mut self) {
+    // Returns true if a node was added.
+    // An output that reads an input directly, like in every new network, grows first:
+    // the new node reads that input and the output reads the new node.
+    pub fn maybe_add_node(&mut self) -> bool {
         if self.property.nodes.len() >= self.configuration.max_network_size {
-            return
+            return false
+        }
+
+        let num_of_input_nodes = self.configuration.num_of_input_nodes;
+        let input_output = self.property.output_indices.iter().position(|index| *index < num_of_input_nodes);
+
+        if input_output.is_none() && self.analyze().has_dead_nodes() {
+            // There are still unused nodes in the network, so no need to add more!
+            return false
         }
 
-        if self.property.has_unused_nodes() {
-            // There are still unused noded in the network, so no need to add more!
-            return
+        let mut node = Node::new_simple(&mut self.rng, &self.mutation_settings);
+
+        if let Some(output) = input_output {
+            let (_, weight) = node.connection(0);
+            node.set_connection(0, self.property.output_indices[output], weight);
+            self.property.output_indices[output] = num_of_input_nodes + self.property.nodes.len();
         }
 
-        self.property.nodes.push(Node::new_simple(&mut self.rng));
+        self.property.nodes.push(node);
         self.nodes_output_values.push(0.0);
+        true
+    }
+
+    fn mutate(&mut self) -> Option<MutationOperation> {
+        let operation = self.property.mutate(&mut self.rng, self.nodes_output_values.len(), self.hyperparameters.node_threshold, &self.mutation_settings);
+        // Structural operations may have changed the number of nodes
+        self.resize_values();
+
+        if cfg!(debug_assertions) && self.configuration.validate_mutations {
+            let violations = self.validate();
+            assert!(violations.is_empty(), "Invalid network {} after mutation {:?}: {:?}", self.id, operation, violations);
+        }
+
+        operation
     }
 
-    fn mutate(&mut self) {
-        self.property.mutate(&mut self.rng, self.nodes_output_values.len(), self.configuration.node_threshold);
+    fn resize_values(&mut self) {
+        let num_of_values = self.configuration.num_of_input_nodes + self.property.nodes.len();
+        self.nodes_output_values.resize(num_of_values, 0.0);
     }
 
-    pub fn optimize_batch(&mut self, indices: &[usize], training_data: &TrainingData) {
+    // Returns the number of error calculations
+    pub(crate) fn optimize<O: Objective>(&mut self, objective: &O) -> usize {
+        if !self.configuration.self_adaptive_hyperparameters {
+            return self.optimize_property(objective)
+        }
+
+        let previous_hyperparameters = self.hyperparameters.clone();
+        let previous_step_scale = self.mutation_settings.step_scale;
+        let start_error = self.best_error;
+        self.hyperparameters.mutate(&mut self.rng, &self.configuration, &mut self.mutation_settings.step_scale);
+
+        let num_of_evaluations = self.optimize_property(objective);
+
+        if self.best_error >= start_error {
+            // The new hyperparameters did not lead to an improvement
+            self.hyperparameters = previous_hyperparameters;
+            self.mutation_settings.step_scale = previous_step_scale;
+        }
+
+        num_of_evaluations
+    }
+
+    fn optimize_property<O: Objective>(&mut self, objective: &O) -> usize {
+        if self.weight_optimizer == WeightOptimizer::SeparableNes {
+            return self.optimize_weights(objective)
+        }
+
         // Initialize
         self.undo_property = self.property.clone();
+        // Network that the next mutation is applied to, only used if worse networks can be accepted.
+        // With hill climbing the rejected mutations stay and are built upon until the next improvement.
+        let mut current_property = self.property.clone();
+        let mut current_error = self.best_error;
+        self.acceptance.start_batch(current_error);
+
+        let num_of_node_mutation = self.hyperparameters.num_of_node_mutation;
 
-        for _ in 0..self.configuration.num_of_node_mutation {
-            self.mutate();
+        for _ in 0..num_of_node_mutation {
+            let operation = self.mutate();
 
-            let batch_error = self.calculate_batch_and_error(indices, training_data);
+            let batch_error = objective.error(self);
+            let success = batch_error < current_error;
+
+            if let Some(operation) = operation {
+                self.operator_statistics.record(operation, success);
+                self.mutation_settings.update(operation, success);
+                self.lineage.add_mutations(&operation.name(), 1, success as u64);
+            }
 
             if batch_error < self.best_error {
                 // Better solution found
                 self.best_error = batch_error;
                 self.undo_property = self.property.clone();
             }
+
+            if !self.acceptance.is_hill_climbing() {
+                if success || self.acceptance.accept(batch_error, current_error, &mut self.rng) {
+                    current_error = batch_error;
+                    current_property = self.property.clone();
+                } else {
+                    self.property = current_property.clone();
+                    self.resize_values();
+                }
+
+                self.acceptance.update(current_error);
+            } else if success {
+                current_error = batch_error;
+            }
         }
 
+        self.acceptance.cool();
+
         // Revert to previous best solution
         self.property = self.undo_property.clone();
+        self.resize_values();
+
+        num_of_node_mutation
+    }
+
+    // Same number of error calculations as optimize() with mutations, but only weights and biases change
+    // Frozen parameters are not part of the search
+    fn optimize_weights<O: Objective>(&mut self, objective: &O) -> usize {
+        let mut parameters = gradient::parameters(&self.property);
+        let free_indices = gradient::free_parameter_indices(&self.property);
+        if free_indices.is_empty() {
+            return 0
+        }
+
+        let mean: Vec<f64> = free_indices.iter().map(|index| parameters[*index]).collect();
+        let mut nes = match self.nes.take() {
+            Some(nes) if nes.num_of_parameters() == mean.len() => nes,
+            // New network or the structure has changed
+            _ => SeparableNes::new(mean.len(), self.configuration.initial_sigma),
+        };
+
+        self.undo_property = self.property.clone();
+        let mut rng = self.rng.clone();
+        let mut best_error = self.best_error;
+        let mut num_of_samples = 0;
+        let mut num_of_improvements = 0;
+
+        let best = nes.run(&mean, self.hyperparameters.num_of_node_mutation, &mut rng, |sample| {
+            for (index, value) in free_indices.iter().zip(sample) {
+                parameters[*index] = *value;
+            }
+            gradient::set_parameters(&mut self.property, &parameters);
+            let error = objective.error(self);
+
+            num_of_samples += 1;
+            if error < best_error {
+                best_error = error;
+                num_of_improvements += 1;
+            }
+
+            error
+        });
+
+        self.rng = rng;
+        self.nes = Some(nes);
+        self.lineage.add_mutations(&format!("{:?}", WeightOptimizer::SeparableNes), num_of_samples, num_of_improvements);
+
+        match best {
+            Some((sample, error)) if error < self.best_error => {
+                for (index, value) in free_indices.iter().zip(&sample) {
+                    parameters[*index] = *value;
+                }
+                gradient::set_parameters(&mut self.property, &parameters);
+                self.best_error = error;
+            }
+            _ => self.property = self.undo_property.clone(),
+        }
+
+        num_of_samples as usize
+    }
+
+    pub fn weight_optimizer(&self) -> WeightOptimizer {
+        self.weight_optimizer
+    }
+
+    // Optimizes weights and biases with gradients, the structure does not change.
+    // Returns the error on the given entries before and after.
+    pub fn fine_tune(&mut self, indices: &[usize], training_data: &TrainingData) -> (f64, f64) {
+        gradient::fine_tune(&mut self.property, &self.configuration, indices, training_data)
     }
 
     pub fn set_property(&mut self, property: Property) {
         self.property = property;
+        self.resize_values();
     }
 
     pub fn get_property(&self) -> Property {
         self.property.clone()
     }
 
-    pub fn fix(&mut self) {
-        self.property.fix(&mut self.rng, &self.configuration);
+    pub fn fix(&mut self) -> RepairReport {
+        let report = self.property.fix(&mut self.rng, &self.configuration);
+
+        self.resize_values();
+        report
+    }
+
+    pub fn validate(&self) -> Vec<PropertyViolation> {
+        self.property.validate(&self.configuration)
+    }
+
+    pub fn analyze(&self) -> GraphAnalysis {
+        self.property.analyze(self.configuration.num_of_input_nodes)
+    }
+
+    pub fn prune_dead_nodes(&mut self) -> usize {
+        let num_of_removed_nodes = self.property.prune_dead_nodes(self.configuration.num_of_input_nodes);
+        self.resize_values();
+        num_of_removed_nodes
+    }
 
-        let num_of_nodes = self.property.nodes.len();
-        let max_connection_index = num_of_nodes + self.configuration.num_of_input_nodes;
+    pub fn freeze_nodes(&mut self, start: usize, end: usize) {
+        self.property.freeze_nodes(start, end);
+    }
 
-        self.nodes_output_values.resize(max_connection_index, 0.0);
+    pub fn unfreeze_all(&mut self) {
+        self.property.unfreeze_all();
     }
 
-    pub fn reset_best_error(&mut self, indices: &[usize], training_data: &TrainingData) {
-        self.best_error = self.calculate_batch_and_error(indices, training_data);
+    // Applies the freeze settings from the configuration to the first num_of_loaded_nodes nodes,
+    // nodes added while adapting the network stay unfrozen.
+    pub fn freeze_loaded_nodes(&mut self, num_of_loaded_nodes: usize) {
+        if self.configuration.freeze_loaded_networks {
+            self.property.freeze_nodes(0, num_of_loaded_nodes);
+        } else {
+            for &(start, end) in &self.configuration.frozen_node_ranges {
+                self.property.freeze_nodes(start, end.min(num_of_loaded_nodes));
+            }
+        }
+    }
+
+    pub(crate) fn reset_best_error<O: Objective>(&mut self, objective: &O) {
+        self.best_error = objective.error(self);
     }
 
     pub fn is_good_enough(&mut self) -> bool {
         self.best_error <= self.configuration.desired_error
     }
 
+    pub fn to_record(&self) -> NetworkRecord {
+        NetworkRecord {
+            state: NetworkState {
+                id: self.id.clone(),
+                best_error: self.best_error,
+                first_place_counter: self.first_place_counter,
+                temperature: self.acceptance.temperature(),
+                weight_optimizer: Some(self.weight_optimizer),
+                nes_sigma: self.nes.as_ref().map(|nes| nes.sigma().to_vec()),
+                operator_statistics: self.operator_statistics.clone(),
+                mutation_state: Some(self.mutation_settings.state()),
+                lineage: Some(self.lineage.clone()),
+                hyperparameters: if self.configuration.self_adaptive_hyperparameters { Some(self.hyperparameters.clone()) } else { None },
+            },
+            property: self.property.clone(),
+        }
+    }
+
+    pub fn from_record(configuration: DriverConfiguration, record: NetworkRecord) -> Network {
+        let NetworkRecord { state, property } = record;
+        let mut network = Network::new_with_property(configuration, property, &state.id);
+        network.best_error = state.best_error;
+        network.first_place_counter = state.first_place_counter;
+        network.operator_statistics = state.operator_statistics;
+        if let Some(temperature) = state.temperature {
+            network.acceptance.set_temperature(temperature);
+        }
+        if let Some(lineage) = state.lineage {
+            network.lineage = lineage;
+        }
+        network.restore_optimizer_state(state.weight_optimizer, state.nes_sigma, state.mutation_state);
+        network.restore_hyperparameters(state.hyperparameters);
+        lineage::reserve_id(&network.id);
+        network
+    }
+
+    // Takes over the training result of the same network from another process.
+    // The lineage of the record only has the mutations of this batch, the history stays here.
+    pub fn update_from_record(&mut self, record: NetworkRecord) {
+        let NetworkRecord { state, property } = record;
+        self.set_property(property);
+        self.best_error = state.best_error;
+        self.operator_statistics = state.operator_statistics;
+        if let Some(temperature) = state.temperature {
+            self.acceptance.set_temperature(temperature);
+        }
+        if let Some(lineage) = state.lineage {
+            self.lineage.merge_mutations(&lineage);
+        }
+        self.restore_optimizer_state(state.weight_optimizer, state.nes_sigma, state.mutation_state);
+        self.restore_hyperparameters(state.hyperparameters);
+    }
+
+    // A saved weight optimizer is kept if the configuration can still choose it
+    fn restore_optimizer_state(&mut self, weight_optimizer: Option<WeightOptimizer>, nes_sigma: Option<Vec<f64>>, mutation_state: Option<MutationState>) {
+        if let Some(weight_optimizer) = weight_optimizer {
+            if can_choose_weight_optimizer(&self.configuration, weight_optimizer) {
+                self.weight_optimizer = weight_optimizer;
+            }
+        }
+        self.nes = nes_sigma.map(SeparableNes::with_sigma);
+        if let Some(mutation_state) = mutation_state {
+            self.mutation_settings.set_state(mutation_state);
+        }
+    }
+
+    // Saved hyperparameters are only used if they are self-adaptive, otherwise the configuration applies
+    fn restore_hyperparameters(&mut self, hyperparameters: Option<Hyperparameters>) {
+        if let Some(hyperparameters) = hyperparameters {
+            if self.configuration.self_adaptive_hyperparameters {
+                self.hyperparameters = hyperparameters;
+            }
+        }
+    }
+
+    // Copy of this network with a new id, the statistics start from zero
+    pub fn new_child(&self, origin: Origin, batch_iteration: usize) -> Network {
+        let mut child = self.clone();
+        child.id = lineage::generate_id("n");
+        child.lineage = self.lineage.child(&child.id, origin, batch_iteration);
+        child.operator_statistics = OperatorStatistics::default();
+        child.first_place_counter = 0;
+        if self.configuration.self_adaptive_hyperparameters {
+            child.hyperparameters.mutate(&mut child.rng, &child.configuration, &mut child.mutation_settings.step_scale);
+        }
+        child
+    }
+
+    pub fn lineage(&self) -> &Lineage {
+        &self.lineage
+    }
+
+    pub fn lineage_mut(&mut self) -> &mut Lineage {
+        &mut self.lineage
+    }
+
+    pub fn operator_statistics(&self) -> &OperatorStatistics {
+        &self.operator_statistics
+    }
+
+    pub fn mutation_settings(&self) -> &MutationSettings {
+        &self.mutation_settings
+    }
+
+    // Only the step scale of the mutation settings changes, the hyperparameters stay as they are
+    pub fn boost_mutation(&mut self, factor: f64) {
+        self.mutation_settings.boost_step_scale(factor);
+    }
+
+    pub fn hyperparameters(&self) -> &Hyperparameters {
+        &self.hyperparameters
+    }
+
+    pub fn acceptance(&self) -> &AcceptanceState {
+        &self.acceptance
+    }
+
+    pub fn num_of_inputs(&self) -> usize {
+        self.configuration.num_of_input_nodes
+    }
+
+    pub fn num_of_outputs(&self) -> usize {
+        self.property.output_indices.len()
+    }
+
     pub fn num_of_nodes(&self) -> usize {
         self.property.nodes.len()
     }
@@ -175,5 +590,61 @@ impl Network {
 
 #[cfg(test)]
 mod test {
-    // use super::*;
+    use super::*;
+
+    use toml;
+
+    use acceptance::{AcceptancePolicy};
+
+    #[test]
+    fn new_network_grows_from_its_outputs() {
+        let configuration: DriverConfiguration = toml::from_str("
+            num_of_input_nodes = 2
+            num_of_output_nodes = 2
+            max_network_size = 10
+        ").unwrap();
+        let mut network = Network::new(configuration);
+        // Every output reads input 0, so the initial node is dead
+        assert!(network.analyze().has_dead_nodes());
+
+        assert!(network.maybe_add_node());
+        assert!(network.maybe_add_node());
+        assert_eq!(network.num_of_nodes(), 3);
+        assert_eq!(network.get_property().output_indices, vec![3, 4]);
+        assert!(network.validate().is_empty());
+
+        // Both outputs read a node now, the initial node is still dead and can be used first
+        assert!(!network.maybe_add_node());
+    }
+
+    #[test]
+    fn reconfiguration_keeps_the_adapted_state() {
+        let mut configuration: DriverConfiguration = toml::from_str("
+            num_of_input_nodes = 2
+            num_of_output_nodes = 1
+            acceptance = \"simulated_annealing\"
+            adaptive_operator_selection = true
+            self_adaptive_hyperparameters = true
+        ").unwrap();
+        let mut network = Network::new(configuration.clone());
+        network.boost_mutation(4.0);
+        network.acceptance.cool();
+        network.hyperparameters.num_of_node_mutation += 7;
+        let mutation_state = network.mutation_settings().state();
+        let temperature = network.acceptance().temperature();
+        let hyperparameters = network.hyperparameters().clone();
+
+        configuration.num_of_iterations += 1;
+        network.set_configuration(configuration.clone());
+        assert_eq!(network.mutation_settings().state(), mutation_state);
+        assert_eq!(network.acceptance().temperature(), temperature);
+        assert_eq!(network.hyperparameters(), &hyperparameters);
+
+        // Without self-adaptation the configuration applies again
+        configuration.self_adaptive_hyperparameters = false;
+        configuration.acceptance = AcceptancePolicy::ThresholdAccepting;
+        network.set_configuration(configuration.clone());
+        assert_eq!(network.hyperparameters(), &Hyperparameters::new(&configuration));
+        assert_eq!(network.acceptance().temperature(), Some(configuration.initial_temperature));
+    }
 }
@@ -1,36 +1,9 @@
 use std::f64;
 
 use rand::{Rng};
-use fnv::FnvHashSet;
-
-const LIMIT1 : f64 = 1.0;
-const LIMIT2 : f64 = 0.001;
-const LIMIT3 : f64 = 0.00001;
-const LIMIT4 : f64 = 0.0000001;
-
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum MutateNodeOperation {
-    SwapConnections,
-    AddConnection,
-    RemoveConnection,
-    RandomConnectionOne,
-    RandomConnectionAll,
-    DeltaBias1,
-    DeltaBias2,
-    DeltaBias3,
-    DeltaBias4,
-    RandomBias,
-    DeltaWeightOne1,
-    DeltaWeightOne2,
-    DeltaWeightOne3,
-    DeltaWeightOne4,
-    RandomWeightOne,
-    DeltaWeightAll1,
-    DeltaWeightAll2,
-    DeltaWeightAll3,
-    DeltaWeightAll4,
-    RandomWeightAll,
-}
+
+use mutation::{MutateNodeOperation, MutationSettings, NODE_OPERATIONS};
+use repair::{RepairReport, RepairChange};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct Connection {
@@ -41,40 +14,28 @@ struct Connection {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Node {
     bias: f64,
+    #[serde(default, skip_serializing_if = "::std::ops::Not::not")]
+    frozen: bool,
     connections: Vec<Connection>,
 }
 
-const NODE_OPERATIONS : [MutateNodeOperation; 20] = [
-    MutateNodeOperation::SwapConnections,
-    MutateNodeOperation::AddConnection,
-    MutateNodeOperation::RemoveConnection,
-    MutateNodeOperation::RandomConnectionOne,
-    MutateNodeOperation::RandomConnectionAll,
-    MutateNodeOperation::DeltaBias1,
-    MutateNodeOperation::DeltaBias2,
-    MutateNodeOperation::DeltaBias3,
-    MutateNodeOperation::DeltaBias4,
-    MutateNodeOperation::RandomBias,
-    MutateNodeOperation::DeltaWeightOne1,
-    MutateNodeOperation::DeltaWeightOne2,
-    MutateNodeOperation::DeltaWeightOne3,
-    MutateNodeOperation::DeltaWeightOne4,
-    MutateNodeOperation::RandomWeightOne,
-    MutateNodeOperation::DeltaWeightAll1,
-    MutateNodeOperation::DeltaWeightAll2,
-    MutateNodeOperation::DeltaWeightAll3,
-    MutateNodeOperation::DeltaWeightAll4,
-    MutateNodeOperation::RandomWeightAll,
-];
-
 impl Node {
-    pub fn new_simple<T: Rng>(rng: &mut T) -> Node {
+    pub fn new(bias: f64) -> Node {
         Node {
-            bias: rng.gen_range::<f64>(-10.0, 10.0),
+            bias,
+            frozen: false,
+            connections: Vec::new(),
+        }
+    }
+
+    pub fn new_simple<T: Rng>(rng: &mut T, settings: &MutationSettings) -> Node {
+        Node {
+            bias: settings.random_bias(rng),
+            frozen: false,
             connections: vec![
                 Connection {
                     index: 0,
-                    weight: rng.gen_range::<f64>(-10.0, 10.0),
+                    weight: settings.random_weight(rng),
                 }
             ],
         }
@@ -93,12 +54,31 @@ impl Node {
         }
     }
 
-    pub fn mutate_node<T: Rng>(&mut self, rng: &mut T, max_connection_index: usize) {
+    pub fn mutate_node<T: Rng>(&mut self, rng: &mut T, max_connection_index: usize, settings: &MutationSettings) -> Option<MutateNodeOperation> {
+        if self.frozen {
+            // Frozen nodes keep their trained bias, weights and connections
+            return None
+        }
+
+        for _ in 0..NODE_OPERATIONS.len() {
+            let operation = settings.choose_node_operation(rng);
+
+            if self.apply_operation(operation, rng, max_connection_index, settings) {
+                return Some(operation)
+            }
+
+            // Operation not possible for this node, try a different mutation
+        }
+
+        None
+    }
+
+    fn apply_operation<T: Rng>(&mut self, operation: MutateNodeOperation, rng: &mut T, max_connection_index: usize, settings: &MutationSettings) -> bool {
         let num_of_connections = self.connections.len();
 
-        use self::MutateNodeOperation::*;
-        match rng.choose(&NODE_OPERATIONS).unwrap() {
-            &SwapConnections => {
+        use mutation::MutateNodeOperation::*;
+        match operation {
+            SwapConnections => {
                 if num_of_connections > 1 {
                     let index1 = rng.gen_range::<usize>(0, num_of_connections);
                     let mut index2 = rng.gen_range::<usize>(0, num_of_connections);
@@ -114,50 +94,50 @@ impl Node {
                     self.connections[index1].index = con_index2;
                     self.connections[index2].index = con_index1;
                 } else {
-                    // No swap possible, try a different mutation
-                    self.mutate_node(rng, max_connection_index);
+                    // No swap possible
+                    return false
                 }
             }
-            &AddConnection => {
+            AddConnection => {
                 // This is faster than using FnvHashSet
                 let possible_connections: Vec<usize> = (0..max_connection_index).filter(
-                    |index| !self.connections.iter().any(|ref connection| connection.index == *index)).collect();
+                    |index| !self.connections.iter().any(|connection| connection.index == *index)).collect();
 
                 if possible_connections.is_empty() {
-                    // No more connections available, try a different mutation
-                    self.mutate_node(rng, max_connection_index);
+                    // No more connections available
+                    return false
                 } else {
                     let index = rng.gen_range::<usize>(0, possible_connections.len());
                     self.connections.push(Connection {
                         index: possible_connections[index],
-                        weight: rng.gen_range::<f64>(-10.0, 10.0),
+                        weight: settings.random_weight(rng),
                     });
                 }
             }
-            &RemoveConnection => {
+            RemoveConnection => {
                 if num_of_connections > 1 {
                     let index = rng.gen_range::<usize>(0, num_of_connections);
                     self.connections.remove(index);
                 } else {
-                    // Keep at least one connection, try a different mutation
-                    self.mutate_node(rng, max_connection_index);
+                    // Keep at least one connection
+                    return false
                 }
             }
-            &RandomConnectionOne => {
+            RandomConnectionOne => {
                 // This is faster than using FnvHashSet
                 let possible_connections: Vec<usize> = (0..max_connection_index).filter(
-                    |index| !self.connections.iter().any(|ref connection| connection.index == *index)).collect();
+                    |index| !self.connections.iter().any(|connection| connection.index == *index)).collect();
 
                 if possible_connections.is_empty() {
-                    // No more connections available, try a different mutation
-                    self.mutate_node(rng, max_connection_index);
+                    // No more connections available
+                    return false
                 } else {
                     let index1 = rng.gen_range::<usize>(0, possible_connections.len());
                     let index2 = rng.gen_range::<usize>(0, num_of_connections);
                     self.connections[index2].index = possible_connections[index1];
                 }
             }
-            &RandomConnectionAll => {
+            RandomConnectionAll => {
                 let mut possible_connections: Vec<usize> = (0..max_connection_index).collect();
                 rng.shuffle(&mut possible_connections);
 
@@ -165,93 +145,173 @@ impl Node {
                     connection.index = index;
                 }
             }
-            &DeltaBias1 => {
-                self.bias += rng.gen_range::<f64>(-LIMIT1, LIMIT1);
+            DeltaBias1 => {
+                self.bias += settings.random_delta(rng, 0);
             }
-            &DeltaBias2 => {
-                self.bias += rng.gen_range::<f64>(-LIMIT2, LIMIT2);
+            DeltaBias2 => {
+                self.bias += settings.random_delta(rng, 1);
             }
-            &DeltaBias3 => {
-                self.bias += rng.gen_range::<f64>(-LIMIT3, LIMIT3);
+            DeltaBias3 => {
+                self.bias += settings.random_delta(rng, 2);
             }
-            &DeltaBias4 => {
-                self.bias += rng.gen_range::<f64>(-LIMIT4, LIMIT4);
+            DeltaBias4 => {
+                self.bias += settings.random_delta(rng, 3);
             }
-            &RandomBias => {
-                self.bias = rng.gen_range::<f64>(-10.0, 10.0);
+            RandomBias => {
+                self.bias = settings.random_bias(rng);
             }
-            &DeltaWeightOne1 => {
+            DeltaWeightOne1 => {
                 let index = rng.gen_range::<usize>(0, num_of_connections);
-                self.connections[index].weight += rng.gen_range::<f64>(-LIMIT1, LIMIT1);
+                self.connections[index].weight += settings.random_delta(rng, 0);
             }
-            &DeltaWeightOne2 => {
+            DeltaWeightOne2 => {
                 let index = rng.gen_range::<usize>(0, num_of_connections);
-                self.connections[index].weight += rng.gen_range::<f64>(-LIMIT2, LIMIT2);
+                self.connections[index].weight += settings.random_delta(rng, 1);
             }
-            &DeltaWeightOne3 => {
+            DeltaWeightOne3 => {
                 let index = rng.gen_range::<usize>(0, num_of_connections);
-                self.connections[index].weight += rng.gen_range::<f64>(-LIMIT3, LIMIT3);
+                self.connections[index].weight += settings.random_delta(rng, 2);
             }
-            &DeltaWeightOne4 => {
+            DeltaWeightOne4 => {
                 let index = rng.gen_range::<usize>(0, num_of_connections);
-                self.connections[index].weight += rng.gen_range::<f64>(-LIMIT4, LIMIT4);
+                self.connections[index].weight += settings.random_delta(rng, 3);
             }
-            &RandomWeightOne => {
+            RandomWeightOne => {
                 let index = rng.gen_range::<usize>(0, num_of_connections);
-                self.connections[index].weight = rng.gen_range::<f64>(-10.0, 10.0);
+                self.connections[index].weight = settings.random_weight(rng);
             }
-            &DeltaWeightAll1 => {
+            DeltaWeightAll1 => {
                 for connection in &mut self.connections {
-                    connection.weight += rng.gen_range::<f64>(-LIMIT1, LIMIT1);
+                    connection.weight += settings.random_delta(rng, 0);
                 }
             }
-            &DeltaWeightAll2 => {
+            DeltaWeightAll2 => {
                 for connection in &mut self.connections {
-                    connection.weight += rng.gen_range::<f64>(-LIMIT2, LIMIT2);
+                    connection.weight += settings.random_delta(rng, 1);
                 }
             }
-            &DeltaWeightAll3 => {
+            DeltaWeightAll3 => {
                 for connection in &mut self.connections {
-                    connection.weight += rng.gen_range::<f64>(-LIMIT3, LIMIT3);
+                    connection.weight += settings.random_delta(rng, 2);
                 }
             }
-            &DeltaWeightAll4 => {
+            DeltaWeightAll4 => {
                 for connection in &mut self.connections {
-                    connection.weight += rng.gen_range::<f64>(-LIMIT4, LIMIT4);
+                    connection.weight += settings.random_delta(rng, 3);
                 }
             }
-            &RandomWeightAll => {
+            RandomWeightAll => {
                 for connection in &mut self.connections {
-                    connection.weight = rng.gen_range::<f64>(-10.0, 10.0);
+                    connection.weight = settings.random_weight(rng);
                 }
             }
         }
-    }
 
-    pub fn fix(&mut self, max_connection_index: usize) {
-        let highest_connection = self.connections.iter().max_by(|c1, c2| c1.index.cmp(&c2.index)).unwrap().index;
+        true
+    }
 
-        if highest_connection >= max_connection_index {
-            let diff = highest_connection - max_connection_index + 1;
+    // Removes connections that read values outside of the network and resets non finite values
+    pub fn fix(&mut self, node_index: usize, max_connection_index: usize, report: &mut RepairReport) {
+        if !self.bias.is_finite() {
+            report.push(RepairChange::BiasReset { node: node_index, old_bias: self.bias });
+            self.bias = 0.0;
+        }
 
-            for connection in &mut self.connections {
-                if connection.index >= diff {
-                    connection.index -= diff;
-                } else {
-                    connection.index = 0;
-                }
+        for connection in &mut self.connections {
+            if !connection.weight.is_finite() {
+                report.push(RepairChange::WeightReset { node: node_index, index: connection.index, old_weight: connection.weight });
+                connection.weight = 0.0;
             }
         }
+
+        for connection in self.connections.iter().filter(|connection| connection.index >= max_connection_index) {
+            report.push(RepairChange::ConnectionRemoved { node: node_index, index: connection.index, weight: connection.weight });
+        }
+
+        self.connections.retain(|connection| connection.index < max_connection_index);
+
+        if self.connections.is_empty() {
+            // Every node needs at least one connection
+            report.push(RepairChange::ConnectionAdded { node: node_index, index: 0, weight: 0.0 });
+            self.add_connection(0, 0.0);
+        }
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    pub fn set_frozen(&mut self, frozen: bool) {
+        self.frozen = frozen;
+    }
+
+    // Rewrites every connection index with the given function.
+    // Connections for which the function returns None are removed.
+    pub fn remap_connections<F: Fn(usize) -> Option<usize>>(&mut self, remap: F) {
+        self.connections = self.connections.iter().filter_map(|connection| {
+            remap(connection.index).map(|index| Connection {
+                index,
+                weight: connection.weight,
+            })
+        }).collect();
+    }
+
+    pub fn has_connection(&self, index: usize) -> bool {
+        self.connections.iter().any(|connection| connection.index == index)
     }
 
-    pub fn add_used_nodes(&self, node_index: usize, set_of_used_nodes: &mut FnvHashSet<usize>) {
-        for connection in &self.connections {
-            if connection.index == node_index {
-                // If node connects to it self it doesn't count as used
-                continue
+    pub fn add_connection(&mut self, index: usize, weight: f64) {
+        self.connections.push(Connection {
+            index,
+            weight,
+        });
+    }
+
+    pub fn num_of_connections(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn bias(&self) -> f64 {
+        self.bias
+    }
+
+    pub fn set_bias(&mut self, bias: f64) {
+        self.bias = bias;
+    }
+
+    pub fn connection(&self, position: usize) -> (usize, f64) {
+        (self.connections[position].index, self.connections[position].weight)
+    }
+
+    pub fn set_connection(&mut self, position: usize, index: usize, weight: f64) {
+        self.connections[position].index = index;
+        self.connections[position].weight = weight;
+    }
+
+    // The connection to old_index is shared with new_index, both get half of the weight.
+    // If both values are equal the result of the node does not change.
+    pub fn share_connection(&mut self, old_index: usize, new_index: usize) {
+        let mut shared = Vec::new();
+
+        for connection in &mut self.connections {
+            if connection.index == old_index {
+                connection.weight *= 0.5;
+                shared.push(Connection {
+                    index: new_index,
+                    weight: connection.weight,
+                });
             }
-            set_of_used_nodes.insert(connection.index);
         }
+
+        self.connections.extend(shared);
+    }
+
+    pub fn max_connection_index(&self) -> Option<usize> {
+        self.connections.iter().map(|connection| connection.index).max()
+    }
+
+    pub fn connection_indices(&self) -> Vec<usize> {
+        self.connections.iter().map(|connection| connection.index).collect()
     }
 }
 
@@ -259,5 +319,56 @@ impl Node {
 
 #[cfg(test)]
 mod test {
-    // use super::*;
+    use super::*;
+
+    use rand::{XorShiftRng, SeedableRng};
+    use toml;
+
+    #[test]
+    fn fix_resets_non_finite_values_and_removes_invalid_connections() {
+        let mut node = Node::new(f64::NAN);
+        node.add_connection(1, f64::INFINITY);
+        node.add_connection(5, 1.0);
+        let mut report = RepairReport::default();
+
+        node.fix(0, 3, &mut report);
+
+        assert_eq!(report.changes.len(), 3);
+        match report.changes[0] {
+            RepairChange::BiasReset { node: 0, old_bias } if old_bias.is_nan() => {}
+            ref change => panic!("Unexpected change: {}", change),
+        }
+        assert_eq!(report.changes[1], RepairChange::WeightReset { node: 0, index: 1, old_weight: f64::INFINITY });
+        assert_eq!(report.changes[2], RepairChange::ConnectionRemoved { node: 0, index: 5, weight: 1.0 });
+        assert_eq!(node.bias(), 0.0);
+        assert_eq!(node.connection_indices(), vec![1]);
+        assert_eq!(node.connection(0), (1, 0.0));
+    }
+
+    #[test]
+    fn frozen_node_is_not_mutated() {
+        let mut rng = XorShiftRng::from_seed([4; 16]);
+        let settings = MutationSettings::new(&toml::from_str("").unwrap());
+        let mut node = Node::new_simple(&mut rng, &settings);
+        node.set_frozen(true);
+        let original = node.clone();
+
+        for _ in 0..100 {
+            assert_eq!(node.mutate_node(&mut rng, 5, &settings), None);
+        }
+        assert_eq!(node, original);
+    }
+
+    #[test]
+    fn shared_connection_keeps_the_result() {
+        let mut node = Node::new(0.5);
+        node.add_connection(0, 2.0);
+        let values = [1.5, 1.5];
+        let result = node.calculate(&values);
+
+        node.share_connection(0, 1);
+
+        assert_eq!(node.connection_indices(), vec![0, 1]);
+        assert_eq!(node.calculate(&values), result);
+    }
 }
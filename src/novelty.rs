@@ -0,0 +1,153 @@
+// Novelty search: networks are also rewarded for behaving differently from the rest of the population
+// and from the behaviours seen before (the archive).
+// The behaviour descriptor of a network is its output vector over a fixed set of probe inputs.
+
+use std::cmp::Ordering;
+
+use driver::{DriverConfiguration, compare_errors};
+use mutation::{ConfigurationError};
+
+pub fn validate(configuration: &DriverConfiguration) -> Result<(), ConfigurationError> {
+    let mut errors = Vec::new();
+
+    if !(configuration.novelty_weight >= 0.0 && configuration.novelty_weight <= 1.0) {
+        errors.push(format!("novelty_weight must be in [0, 1], given: {}", configuration.novelty_weight));
+    }
+
+    if configuration.novelty_k == 0 {
+        errors.push("novelty_k must be > 0".to_string());
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ConfigurationError { errors })
+    }
+}
+
+// Behaviours of earlier networks, the oldest are removed first
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NoveltyArchive {
+    descriptors: Vec<Vec<f64>>,
+}
+
+impl NoveltyArchive {
+    pub fn new(descriptors: Vec<Vec<f64>>) -> NoveltyArchive {
+        NoveltyArchive {
+            descriptors,
+        }
+    }
+
+    pub fn descriptors(&self) -> &[Vec<f64>] {
+        &self.descriptors
+    }
+
+    pub fn len(&self) -> usize {
+        self.descriptors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.descriptors.is_empty()
+    }
+
+    // max_size 0: no limit
+    pub fn add(&mut self, descriptor: Vec<f64>, max_size: usize) {
+        self.descriptors.push(descriptor);
+
+        if max_size > 0 && self.descriptors.len() > max_size {
+            let num_of_removed = self.descriptors.len() - max_size;
+            self.descriptors.drain(..num_of_removed);
+        }
+    }
+}
+
+// NaN or inf outputs would make every distance meaningless
+pub fn sanitize(mut descriptor: Vec<f64>) -> Vec<f64> {
+    for value in &mut descriptor {
+        if !value.is_finite() {
+            *value = 0.0;
+        }
+    }
+
+    descriptor
+}
+
+pub fn distance(descriptor1: &[f64], descriptor2: &[f64]) -> f64 {
+    descriptor1.iter().zip(descriptor2).map(|(value1, value2)| (value1 - value2) * (value1 - value2)).sum::<f64>().sqrt()
+}
+
+// Mean distance to the k nearest neighbours in the population (without itself) and the archive
+pub fn novelty_scores(population: &[Vec<f64>], archive: &NoveltyArchive, k: usize) -> Vec<f64> {
+    population.iter().enumerate().map(|(index, descriptor)| {
+        let mut distances: Vec<f64> = population.iter().enumerate()
+            .filter(|(other_index, _)| *other_index != index)
+            .map(|(_, other)| other)
+            .chain(archive.descriptors())
+            .map(|other| distance(descriptor, other))
+            .collect();
+
+        if distances.is_empty() {
+            return 0.0
+        }
+
+        distances.sort_by(|d1, d2| d1.partial_cmp(d2).unwrap_or(Ordering::Equal));
+        let num_of_neighbours = k.min(distances.len());
+
+        distances[..num_of_neighbours].iter().sum::<f64>() / (num_of_neighbours as f64)
+    }).collect()
+}
+
+// Rank of each entry, 0 for the best one
+fn ranks<F: Fn(usize, usize) -> Ordering>(len: usize, compare: F) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..len).collect();
+    order.sort_by(|i1, i2| compare(*i1, *i2));
+
+    let mut result = vec![0; len];
+    for (rank, index) in order.into_iter().enumerate() {
+        result[index] = rank;
+    }
+
+    result
+}
+
+// Selection score, lower is better: weighted sum of the error rank and the novelty rank.
+// Ranks are used because errors and distances have unrelated scales.
+pub fn blended_scores(errors: &[f64], novelty: &[f64], novelty_weight: f64) -> Vec<f64> {
+    let error_ranks = ranks(errors.len(), |i1, i2| compare_errors(errors[i1], errors[i2]));
+    // Most novel first
+    let novelty_ranks = ranks(novelty.len(), |i1, i2| novelty[i2].partial_cmp(&novelty[i1]).unwrap_or(Ordering::Equal));
+
+    error_ranks.iter().zip(novelty_ranks).map(|(error_rank, novelty_rank)| {
+        ((1.0 - novelty_weight) * (*error_rank as f64)) + (novelty_weight * (novelty_rank as f64))
+    }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn outlier_is_most_novel() {
+        let population = vec![vec![0.0, 0.0], vec![0.1, 0.0], vec![0.0, 0.1], vec![5.0, 5.0]];
+        let mut archive = NoveltyArchive::default();
+        let scores = novelty_scores(&population, &archive, 2);
+
+        assert!(scores[3] > scores[0] && scores[3] > scores[1] && scores[3] > scores[2]);
+
+        // Once the outlier behaviour is known, it is not novel anymore
+        archive.add(vec![5.0, 5.0], 1);
+        archive.add(vec![5.0, 5.1], 1);
+        assert_eq!(archive.descriptors(), &[vec![5.0, 5.1]]);
+        assert!(novelty_scores(&population, &archive, 1)[3] < 0.2);
+    }
+
+    #[test]
+    fn blend_of_error_and_novelty_ranks() {
+        let errors = [0.1, 0.2, 0.3];
+        let novelty = [0.0, 1.0, 2.0];
+
+        assert_eq!(blended_scores(&errors, &novelty, 0.0), vec![0.0, 1.0, 2.0]);
+        assert_eq!(blended_scores(&errors, &novelty, 1.0), vec![2.0, 1.0, 0.0]);
+        assert_eq!(blended_scores(&errors, &novelty, 0.5), vec![1.0, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,260 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::Error;
+use toml;
+
+use mutation::{MutationState, OperatorStatistics};
+use nes::{WeightOptimizer};
+use lineage::{Lineage};
+use property::{Property};
+use hyperparameters::{Hyperparameters};
+
+// One network of the population together with its training state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkRecord {
+    #[serde(flatten)]
+    pub state: NetworkState,
+    pub property: Property,
+}
+
+// Everything of a NetworkRecord except the property, which the directory format stores in its own file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkState {
+    pub id: String,
+    pub best_error: f64,
+    pub first_place_counter: u64,
+    // Current temperature (or threshold) of the acceptance policy
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub weight_optimizer: Option<WeightOptimizer>,
+    // Step sizes of the evolution strategy
+    #[serde(default)]
+    pub nes_sigma: Option<Vec<f64>>,
+    #[serde(default)]
+    pub operator_statistics: OperatorStatistics,
+    // Adapted operator weights and step scale
+    #[serde(default)]
+    pub mutation_state: Option<MutationState>,
+    #[serde(default)]
+    pub lineage: Option<Lineage>,
+    // Mutation parameters of the network, see DriverConfiguration::self_adaptive_hyperparameters
+    #[serde(default)]
+    pub hyperparameters: Option<Hyperparameters>,
+}
+
+// All networks in a single file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct PopulationArchive {
+    #[serde(default)]
+    pub batch_iteration: Option<usize>,
+    // Behaviour descriptors of novelty search
+    #[serde(default)]
+    pub novelty_archive: Vec<Vec<f64>>,
+    pub networks: Vec<NetworkRecord>,
+}
+
+// Index file for the directory format, the properties are stored in separate files
+// that can also be loaded with Driver::load_network
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PopulationManifest {
+    #[serde(default)]
+    batch_iteration: Option<usize>,
+    #[serde(default)]
+    novelty_archive: Vec<Vec<f64>>,
+    networks: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ManifestEntry {
+    file: String,
+    #[serde(flatten)]
+    state: NetworkState,
+}
+
+const MANIFEST_FILE : &str = "population.toml";
+const SNAPSHOT_PREFIX : &str = "population_";
+
+fn sanitize(id: &str) -> String {
+    id.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+}
+
+impl PopulationArchive {
+    pub fn save<P: AsRef<Path>>(&self, filename: P) -> Result<(), Error> {
+        let serialized = toml::Value::try_from(self)?.to_string();
+        fs::write(filename, serialized)?;
+
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(filename: P) -> Result<PopulationArchive, Error> {
+        Ok(toml::from_str(&fs::read_to_string(filename)?)?)
+    }
+
+    pub fn save_directory<P: AsRef<Path>>(&self, directory: P) -> Result<(), Error> {
+        let directory = directory.as_ref();
+        fs::create_dir_all(directory)?;
+
+        let mut entries = Vec::with_capacity(self.networks.len());
+
+        for (index, record) in self.networks.iter().enumerate() {
+            let file = format!("{:04}_{}.toml", index, sanitize(&record.state.id));
+            let serialized = toml::Value::try_from(&record.property)?.to_string();
+            fs::write(directory.join(&file), serialized)?;
+
+            entries.push(ManifestEntry {
+                file,
+                state: record.state.clone(),
+            });
+        }
+
+        let manifest = PopulationManifest {
+            batch_iteration: self.batch_iteration,
+            novelty_archive: self.novelty_archive.clone(),
+            networks: entries,
+        };
+        fs::write(directory.join(MANIFEST_FILE), toml::Value::try_from(&manifest)?.to_string())?;
+
+        Ok(())
+    }
+
+    pub fn load_directory<P: AsRef<Path>>(directory: P) -> Result<PopulationArchive, Error> {
+        let directory = directory.as_ref();
+        let manifest: PopulationManifest = toml::from_str(&fs::read_to_string(directory.join(MANIFEST_FILE))?)?;
+        let mut networks = Vec::with_capacity(manifest.networks.len());
+
+        for entry in manifest.networks {
+            let property = toml::from_str(&fs::read_to_string(directory.join(&entry.file))?)?;
+
+            networks.push(NetworkRecord {
+                state: entry.state,
+                property,
+            });
+        }
+
+        Ok(PopulationArchive {
+            batch_iteration: manifest.batch_iteration,
+            novelty_archive: manifest.novelty_archive,
+            networks,
+        })
+    }
+}
+
+// Writes one archive per batch iteration and keeps only the newest ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotRotation {
+    pub directory: PathBuf,
+    // 0: keep all snapshots
+    pub max_num_of_snapshots: usize,
+}
+
+impl SnapshotRotation {
+    pub fn snapshot_file(&self, batch_iteration: usize) -> PathBuf {
+        self.directory.join(format!("{}{:06}.toml", SNAPSHOT_PREFIX, batch_iteration))
+    }
+
+    // All snapshot files, oldest first
+    pub fn snapshots(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut result: Vec<PathBuf> = fs::read_dir(&self.directory)?.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| {
+            path.file_name().and_then(|name| name.to_str()).map_or(false, |name| name.starts_with(SNAPSHOT_PREFIX) && name.ends_with(".toml"))
+        }).collect();
+
+        result.sort();
+        Ok(result)
+    }
+
+    pub fn save(&self, archive: &PopulationArchive, batch_iteration: usize) -> Result<(), Error> {
+        fs::create_dir_all(&self.directory)?;
+        archive.save(self.snapshot_file(batch_iteration))?;
+
+        if self.max_num_of_snapshots > 0 {
+            let snapshots = self.snapshots()?;
+
+            if snapshots.len() > self.max_num_of_snapshots {
+                for old_snapshot in &snapshots[..snapshots.len() - self.max_num_of_snapshots] {
+                    fs::remove_file(old_snapshot)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_support::{TempPath};
+
+    fn archive() -> PopulationArchive {
+        let property: Property = toml::from_str(include_str!("trained_networks/xor02.toml")).unwrap();
+
+        PopulationArchive {
+            batch_iteration: Some(3),
+            novelty_archive: vec![vec![0.0, 1.0], vec![0.5, 0.25]],
+            networks: vec![
+                NetworkRecord {
+                    state: NetworkState {
+                        id: "n1".to_string(),
+                        best_error: 0.5,
+                        first_place_counter: 2,
+                        temperature: Some(0.5),
+                        weight_optimizer: Some(WeightOptimizer::SeparableNes),
+                        nes_sigma: Some(vec![0.1, 0.2]),
+                        operator_statistics: OperatorStatistics::default(),
+                        mutation_state: None,
+                        lineage: None,
+                        hyperparameters: Some(Hyperparameters { node_threshold: 0.2, num_of_node_mutation: 50 }),
+                    },
+                    property: property.clone(),
+                },
+                NetworkRecord {
+                    state: NetworkState {
+                        id: "n/2".to_string(),
+                        best_error: f64::MAX,
+                        first_place_counter: 0,
+                        temperature: None,
+                        weight_optimizer: None,
+                        nes_sigma: None,
+                        operator_statistics: OperatorStatistics::default(),
+                        mutation_state: None,
+                        lineage: None,
+                        hyperparameters: None,
+                    },
+                    property,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let filename = TempPath::new("population_archive.toml");
+        archive().save(&filename).unwrap();
+
+        assert_eq!(PopulationArchive::load(&filename).unwrap(), archive());
+    }
+
+    #[test]
+    fn directory_round_trip() {
+        let directory = TempPath::new("population_directory");
+        archive().save_directory(&directory).unwrap();
+
+        let loaded = PopulationArchive::load_directory(&directory).unwrap();
+        assert_eq!(loaded, archive());
+        assert!(directory.join("0001_n_2.toml").exists());
+    }
+
+    #[test]
+    fn rotation_keeps_newest_snapshots() {
+        let directory = TempPath::new("population_rotation");
+        let rotation = SnapshotRotation { directory: directory.to_path_buf(), max_num_of_snapshots: 2 };
+
+        for batch_iteration in 0..5 {
+            rotation.save(&archive(), batch_iteration).unwrap();
+        }
+
+        assert_eq!(rotation.snapshots().unwrap(), vec![rotation.snapshot_file(3), rotation.snapshot_file(4)]);
+    }
+}
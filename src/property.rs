@@ -1,16 +1,9 @@
 use rand::{Rng};
-use fnv::FnvHashSet;
 
 use driver::{DriverConfiguration};
 use node::{Node};
-
-#[derive(Debug, Copy, Clone, PartialEq)]
-enum MutatePropertyOperation {
-    SwapNodes,
-    SwapOutput,
-    RandomOutputOne,
-    RandomOutputAll,
-}
+use mutation::{MutationSettings, MutationOperation, MutatePropertyOperation, PROPERTY_OPERATIONS};
+use repair::{RepairReport, RepairChange};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Property {
@@ -18,79 +11,264 @@ pub struct Property {
     pub output_indices: Vec<usize>,
 }
 
-const PROPERTY_OPERATIONS : [MutatePropertyOperation; 4] = [
-    MutatePropertyOperation::SwapNodes,
-    MutatePropertyOperation::SwapOutput,
-    MutatePropertyOperation::RandomOutputOne,
-    MutatePropertyOperation::RandomOutputAll,
-];
-
 impl Property {
-    pub fn mutate<T: Rng>(&mut self, rng: &mut T, max_connection_index: usize, node_threshold: f64) {
+    // Returns the operation that was applied
+    pub fn mutate<T: Rng>(&mut self, rng: &mut T, max_connection_index: usize, node_threshold: f64, settings: &MutationSettings) -> Option<MutationOperation> {
         let value = rng.gen_range::<f64>(0.0, 1.0);
+        let unfrozen_nodes = self.unfrozen_nodes();
 
-        if value < node_threshold {
-            let num_of_nodes = self.nodes.len();
-            let node_index = rng.gen_range::<usize>(0, num_of_nodes);
-            self.nodes[node_index].mutate_node(rng, max_connection_index);
+        if value < node_threshold && !unfrozen_nodes.is_empty() {
+            let node_index = *rng.choose(&unfrozen_nodes).unwrap();
+            self.nodes[node_index].mutate_node(rng, max_connection_index, settings).map(MutationOperation::Node)
         } else {
-            self.mutate_property(rng, max_connection_index);
+            self.mutate_property(rng, max_connection_index, settings).map(MutationOperation::Property)
         }
     }
 
-    fn mutate_property<T: Rng>(&mut self, rng: &mut T, max_connection_index: usize) {
-        let num_of_nodes = self.nodes.len();
-        let num_of_outputs = self.output_indices.len();
-
-        use self::MutatePropertyOperation::*;
-        match rng.choose(&PROPERTY_OPERATIONS).unwrap() {
-            &SwapNodes => {
-                let index1 = rng.gen_range::<usize>(0, num_of_nodes);
-                let index2 = rng.gen_range::<usize>(0, num_of_nodes);
-                self.nodes.swap(index1, index2);
+    fn mutate_property<T: Rng>(&mut self, rng: &mut T, max_connection_index: usize, settings: &MutationSettings) -> Option<MutatePropertyOperation> {
+        for _ in 0..PROPERTY_OPERATIONS.len() {
+            let operation = settings.choose_property_operation(rng);
+
+            if self.apply_property_operation(operation, rng, max_connection_index, settings) {
+                return Some(operation)
             }
-            &SwapOutput => {
-                let index1 = rng.gen_range::<usize>(0, num_of_outputs);
-                let index2 = rng.gen_range::<usize>(0, num_of_outputs);
-                self.output_indices.swap(index1, index2);
+
+            // Operation not possible or without effect for this network, try a different mutation
+        }
+
+        None
+    }
+
+    // Returns false if the property did not change
+    fn apply_property_operation<T: Rng>(&mut self, operation: MutatePropertyOperation, rng: &mut T, max_connection_index: usize, settings: &MutationSettings) -> bool {
+        let num_of_input_nodes = max_connection_index - self.nodes.len();
+
+        use mutation::MutatePropertyOperation::*;
+        match operation {
+            SwapNodes => {
+                // Moving a frozen node or a node that a frozen node reads would change what the frozen node computes
+                let movable_nodes = self.movable_nodes(num_of_input_nodes);
+
+                match choose_two(rng, &movable_nodes) {
+                    Some((index1, index2)) => self.nodes.swap(index1, index2),
+                    None => return false,
+                }
             }
-            &RandomOutputOne => {
-                let index1 = rng.gen_range::<usize>(0, num_of_outputs);
-                let index2 = rng.gen_range::<usize>(0, max_connection_index);
-                self.output_indices[index1] = index2;
+            SwapOutput => {
+                let unfrozen_outputs = self.unfrozen_outputs(num_of_input_nodes);
+
+                match choose_two(rng, &unfrozen_outputs) {
+                    Some((index1, index2)) if self.output_indices[index1] != self.output_indices[index2] => self.output_indices.swap(index1, index2),
+                    _ => return false,
+                }
             }
-            &RandomOutputAll => {
-                for index in &mut self.output_indices {
-                    *index = rng.gen_range::<usize>(0, max_connection_index);
+            RandomOutputOne => {
+                let unfrozen_outputs = self.unfrozen_outputs(num_of_input_nodes);
+
+                match rng.choose(&unfrozen_outputs) {
+                    Some(index1) => {
+                        let index2 = rng.gen_range::<usize>(0, max_connection_index);
+                        if self.output_indices[*index1] == index2 {
+                            return false
+                        }
+                        self.output_indices[*index1] = index2;
+                    }
+                    None => return false,
                 }
             }
+            RandomOutputAll => {
+                let old_output_indices = self.output_indices.clone();
+
+                for index1 in self.unfrozen_outputs(num_of_input_nodes) {
+                    self.output_indices[index1] = rng.gen_range::<usize>(0, max_connection_index);
+                }
+
+                if self.output_indices == old_output_indices {
+                    return false
+                }
+            }
+            SplitConnection => {
+                let unfrozen_nodes = self.unfrozen_nodes();
+
+                if self.nodes.len() >= settings.max_network_size || unfrozen_nodes.is_empty() {
+                    return false
+                }
+
+                let node_index = *rng.choose(&unfrozen_nodes).unwrap();
+                let position = rng.gen_range::<usize>(0, self.nodes[node_index].num_of_connections());
+                self.split_connection(num_of_input_nodes, node_index, position);
+            }
+            RemoveNode => {
+                let removable_nodes = self.removable_nodes(num_of_input_nodes);
+
+                match rng.choose(&removable_nodes) {
+                    Some(node_index) => self.remove_node(num_of_input_nodes, *node_index),
+                    None => return false,
+                }
+            }
+            DuplicateNode => {
+                if self.nodes.len() >= settings.max_network_size {
+                    return false
+                }
+
+                let node_index = rng.gen_range::<usize>(0, self.nodes.len());
+                self.duplicate_node(num_of_input_nodes, node_index);
+            }
         }
+
+        true
     }
 
-    pub fn has_unused_nodes(&self) -> bool {
-        let mut set_of_used_nodes = FnvHashSet::default();
+    // Inserts a new node into the given connection: source -> new node -> node.
+    // The new node is inserted in front of the node, so the node reads its value in the same cycle,
+    // and every reference to the nodes behind it is renumbered.
+    // The split is not neutral: the new node uses the leaky ReLU, so negative values are scaled down.
+    pub fn split_connection(&mut self, num_of_input_nodes: usize, node_index: usize, position: usize) {
+        let (source, weight) = self.nodes[node_index].connection(position);
+        let new_index = num_of_input_nodes + node_index;
+        let shift = |index: usize| if index >= new_index { index + 1 } else { index };
+
+        let mut new_node = Node::new(0.0);
+        new_node.add_connection(shift(source), weight);
+
+        for node in &mut self.nodes {
+            node.remap_connections(|index| Some(shift(index)));
+        }
+
+        for index in &mut self.output_indices {
+            *index = shift(*index);
+        }
+
+        self.nodes.insert(node_index, new_node);
+        self.nodes[node_index + 1].set_connection(position, new_index, 1.0);
+    }
 
-        for index in 0..self.output_indices.len() {
-            set_of_used_nodes.insert(index);
+    // Nodes that can be removed without changing an output or a frozen node.
+    // Dead nodes come first: if there are any, only those are returned.
+    fn removable_nodes(&self, num_of_input_nodes: usize) -> Vec<usize> {
+        if self.nodes.len() < 2 {
+            return Vec::new()
         }
 
-        for index in 0..self.nodes.len() {
-            self.nodes[index].add_used_nodes(index, &mut set_of_used_nodes);
+        let removable_nodes: Vec<usize> = (0..self.nodes.len()).filter(|index| {
+            let value_index = num_of_input_nodes + index;
+
+            !self.nodes[*index].is_frozen() &&
+                !self.output_indices.contains(&value_index) &&
+                !self.is_read_by_frozen_node(value_index)
+        }).collect();
+
+        let analysis = self.analyze(num_of_input_nodes);
+        let dead_nodes: Vec<usize> = removable_nodes.iter().cloned().filter(|index| !analysis.is_reachable(*index)).collect();
+
+        if dead_nodes.is_empty() {
+            removable_nodes
+        } else {
+            dead_nodes
         }
+    }
+
+    // Removes the node and renumbers every reference to the nodes behind it
+    pub fn remove_node(&mut self, num_of_input_nodes: usize, node_index: usize) {
+        let value_index = num_of_input_nodes + node_index;
 
-        for index in 0..self.nodes.len() {
-            if !set_of_used_nodes.contains(&index) {
-                return true
+        self.nodes.remove(node_index);
+
+        for node in &mut self.nodes {
+            node.remap_connections(|index| {
+                if index == value_index {
+                    None
+                } else if index > value_index {
+                    Some(index - 1)
+                } else {
+                    Some(index)
+                }
+            });
+
+            if node.num_of_connections() == 0 {
+                // Every node needs at least one connection
+                node.add_connection(0, 0.0);
+            }
+        }
+
+        for index in &mut self.output_indices {
+            if *index > value_index {
+                *index -= 1;
+            } else if *index == value_index {
+                *index = 0;
+            }
+        }
+    }
+
+    // Appends a copy of the node, every node that reads the original now reads
+    // both with half of the weight.
+    pub fn duplicate_node(&mut self, num_of_input_nodes: usize, node_index: usize) {
+        let value_index = num_of_input_nodes + node_index;
+        let new_index = num_of_input_nodes + self.nodes.len();
+
+        let mut new_node = self.nodes[node_index].clone();
+        new_node.set_frozen(false);
+
+        for node in &mut self.nodes {
+            if !node.is_frozen() {
+                node.share_connection(value_index, new_index);
             }
         }
 
-        return false
+        self.nodes.push(new_node);
     }
 
-    pub fn fix<T: Rng>(&mut self, rng: &mut T, configuration: &DriverConfiguration) {
+    fn unfrozen_nodes(&self) -> Vec<usize> {
+        (0..self.nodes.len()).filter(|index| !self.nodes[*index].is_frozen()).collect()
+    }
+
+    // Unfrozen nodes that no frozen node reads
+    fn movable_nodes(&self, num_of_input_nodes: usize) -> Vec<usize> {
+        self.unfrozen_nodes().into_iter().filter(|index| !self.is_read_by_frozen_node(num_of_input_nodes + index)).collect()
+    }
+
+    fn is_read_by_frozen_node(&self, value_index: usize) -> bool {
+        self.nodes.iter().any(|node| node.is_frozen() && node.has_connection(value_index))
+    }
+
+    // Outputs that read a frozen node stay where they are
+    fn unfrozen_outputs(&self, num_of_input_nodes: usize) -> Vec<usize> {
+        (0..self.output_indices.len()).filter(|index| {
+            let value_index = self.output_indices[*index];
+            value_index < num_of_input_nodes ||
+                self.nodes.get(value_index - num_of_input_nodes).map_or(true, |node| !node.is_frozen())
+        }).collect()
+    }
+
+    pub fn freeze_nodes(&mut self, start: usize, end: usize) {
+        let end = end.min(self.nodes.len());
+
+        for node in self.nodes.iter_mut().take(end).skip(start) {
+            node.set_frozen(true);
+        }
+    }
+
+    pub fn unfreeze_all(&mut self) {
+        for node in &mut self.nodes {
+            node.set_frozen(false);
+        }
+    }
+
+    pub fn num_of_frozen_nodes(&self) -> usize {
+        self.nodes.iter().filter(|node| node.is_frozen()).count()
+    }
+
+    // Changes the network until it fits the configuration, every change is reported
+    pub fn fix<T: Rng>(&mut self, rng: &mut T, configuration: &DriverConfiguration) -> RepairReport {
+        let mut report = RepairReport::default();
+
         if self.nodes.is_empty() {
-            self.nodes.push(Node::new_simple(rng));
-        } else {
+            self.nodes.push(Node::new_simple(rng, &MutationSettings::new(configuration)));
+            report.push(RepairChange::AddedNode);
+        } else if self.nodes.len() > configuration.max_network_size {
+            report.push(RepairChange::RemovedNodes {
+                num_of_nodes: self.nodes.len() - configuration.max_network_size,
+                max_network_size: configuration.max_network_size });
             self.nodes.truncate(configuration.max_network_size);
         }
 
@@ -98,21 +276,161 @@ impl Property {
 
         let max_connection_index = num_of_nodes + configuration.num_of_input_nodes;
 
-        self.output_indices.resize(configuration.num_of_output_nodes, 0);
+        if self.output_indices.len() != configuration.num_of_output_nodes {
+            report.push(RepairChange::ResizedOutputs { old_len: self.output_indices.len(), new_len: configuration.num_of_output_nodes });
+            self.output_indices.resize(configuration.num_of_output_nodes, 0);
+        }
 
-        for index in &mut self.output_indices {
+        for (output, index) in self.output_indices.iter_mut().enumerate() {
             if *index >= max_connection_index {
-                *index = rng.gen_range::<usize>(0, max_connection_index);
+                let new_index = rng.gen_range::<usize>(0, max_connection_index);
+                report.push(RepairChange::OutputRedirected { output, old_index: *index, new_index });
+                *index = new_index;
             }
         }
 
-        for node in &mut self.nodes {
-            node.fix(max_connection_index);
+        for (node_index, node) in self.nodes.iter_mut().enumerate() {
+            node.fix(node_index, max_connection_index, &mut report);
         }
+
+        report
     }
 }
 
+// Two different entries of the candidates, None if there are less than two
+fn choose_two<T: Rng>(rng: &mut T, candidates: &[usize]) -> Option<(usize, usize)> {
+    if candidates.len() < 2 {
+        return None
+    }
+
+    let position1 = rng.gen_range::<usize>(0, candidates.len());
+    let position2 = (position1 + rng.gen_range::<usize>(1, candidates.len())) % candidates.len();
+
+    Some((candidates[position1], candidates[position2]))
+}
+
 #[cfg(test)]
 mod test {
-    // use super::*;
+    use super::*;
+
+    use rand::{XorShiftRng, SeedableRng};
+    use toml;
+
+    fn xor02() -> Property {
+        toml::from_str(include_str!("trained_networks/xor02.toml")).unwrap()
+    }
+
+    #[test]
+    fn frozen_nodes_are_not_mutated() {
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+        let mut property = xor02();
+        property.output_indices = vec![2];
+        property.freeze_nodes(0, 2);
+        let original = property.clone();
+        let settings = MutationSettings::new(&toml::from_str("").unwrap());
+
+        for _ in 0..1000 {
+            let max_connection_index = 2 + property.nodes.len();
+            property.mutate(&mut rng, max_connection_index, 0.5, &settings);
+        }
+
+        // Structural operations may add unfrozen nodes, the frozen ones stay as they are
+        assert_eq!(property.nodes[0..2], original.nodes[..]);
+        assert_eq!(property.output_indices, original.output_indices);
+    }
+
+    #[test]
+    fn nodes_read_by_frozen_nodes_are_not_moved() {
+        let mut property = xor02();
+        // Node 0 reads node 1 (value index 3)
+        property.freeze_nodes(0, 1);
+        property.duplicate_node(2, 1);
+
+        assert_eq!(property.movable_nodes(2), vec![2]);
+    }
+
+    #[test]
+    fn remove_node_renumbers_references() {
+        let mut property = xor02();
+        property.output_indices = vec![3];
+        property.nodes[1].add_connection(3, 1.0);
+
+        property.remove_node(2, 0);
+
+        assert_eq!(property.nodes.len(), 1);
+        assert_eq!(property.output_indices, vec![2]);
+        // Connection to the removed node (index 2) is gone, self connection 3 -> 2
+        assert!(!property.nodes[0].has_connection(3));
+        assert!(property.nodes[0].has_connection(2));
+    }
+
+    #[test]
+    fn split_inserts_node_in_front_of_reader() {
+        let mut property = xor02();
+        property.output_indices = vec![3];
+        let (source, weight) = property.nodes[1].connection(0);
+
+        property.split_connection(2, 1, 0);
+        assert_eq!(property.nodes.len(), 3);
+        // The new node is node 1 (value index 3), the old node 1 moved to node 2
+        assert_eq!(property.nodes[1].connection(0), (source, weight));
+        assert_eq!(property.nodes[2].connection(0), (3, 1.0));
+        assert_eq!(property.output_indices, vec![4]);
+
+        property.duplicate_node(2, 1);
+        assert_eq!(property.nodes.len(), 4);
+        assert_eq!(property.output_indices, vec![4]);
+    }
+
+    #[test]
+    fn operations_without_effect_are_not_reported() {
+        let mut rng = XorShiftRng::from_seed([2; 16]);
+        let mut property = xor02();
+        property.output_indices = vec![3];
+        property.freeze_nodes(0, 2);
+        let settings = MutationSettings::new(&toml::from_str("max_network_size = 2").unwrap());
+
+        for _ in 0..100 {
+            assert_eq!(property.mutate(&mut rng, 4, 0.0, &settings), None);
+        }
+    }
+
+    #[test]
+    fn fix_reports_every_change() {
+        let configuration: DriverConfiguration = toml::from_str("
+            num_of_input_nodes = 2
+            num_of_output_nodes = 2
+            max_network_size = 1
+        ").unwrap();
+        let mut rng = XorShiftRng::from_seed([3; 16]);
+        let mut property = xor02();
+        property.output_indices = vec![0];
+
+        let report = property.fix(&mut rng, &configuration);
+
+        // Node 1 is removed, so node 0 loses its connection to value index 3
+        assert_eq!(report.changes[0], RepairChange::RemovedNodes { num_of_nodes: 1, max_network_size: 1 });
+        assert_eq!(report.changes[1], RepairChange::ResizedOutputs { old_len: 1, new_len: 2 });
+        match report.changes[2] {
+            RepairChange::ConnectionRemoved { node: 0, index: 3, .. } => {}
+            ref change => panic!("Unexpected change: {}", change),
+        }
+        assert_eq!(report.changes.len(), 3);
+        assert!(property.validate(&configuration).is_empty());
+
+        // Fixing again does not change anything
+        assert!(property.fix(&mut rng, &configuration).is_empty());
+    }
+
+    #[test]
+    fn frozen_flag_is_saved() {
+        let mut property = xor02();
+        property.freeze_nodes(1, 2);
+
+        let serialized = toml::Value::try_from(&property).unwrap().to_string();
+        let loaded: Property = toml::from_str(&serialized).unwrap();
+
+        assert_eq!(loaded.num_of_frozen_nodes(), 1);
+        assert!(loaded.nodes[1].is_frozen());
+    }
 }
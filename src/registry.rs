@@ -0,0 +1,330 @@
+use std::fs;
+use std::cmp::Ordering;
+use std::path::Path;
+
+use failure::Error;
+use toml;
+
+use adaptation::{ShapeMapping};
+use driver::{DriverConfiguration};
+use network::{Network};
+use property::{Property};
+use validation::{InvalidNetwork};
+use lineage::{self, Origin};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrainedNetworkInfo {
+    pub name: String,
+    pub task: String,
+    pub num_of_input_nodes: usize,
+    pub num_of_output_nodes: usize,
+    #[serde(default)]
+    pub error: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrainedNetwork {
+    pub info: TrainedNetworkInfo,
+    pub property: Property,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Compatibility {
+    // Same number of input and output nodes, can be used as is
+    Exact,
+    // Different shape, the network has to be adapted first
+    Adaptable,
+    // Too many nodes for the configured max_network_size
+    Incompatible,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct RegistryManifest {
+    networks: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct ManifestEntry {
+    file: String,
+    #[serde(flatten)]
+    info: TrainedNetworkInfo,
+}
+
+// name, task, number of input nodes, number of output nodes, error, file content
+// The error is the total error over the training data of the matching example (xor1, adder1, iris1), rounded up.
+const BUNDLED_NETWORKS: [(&str, &str, usize, usize, f64, &str); 15] = [
+    ("xor02", "xor", 2, 1, 0.001, include_str!("trained_networks/xor02.toml")),
+    ("xor03", "xor", 2, 1, 0.001, include_str!("trained_networks/xor03.toml")),
+    ("xor04", "xor", 2, 1, 0.001, include_str!("trained_networks/xor04.toml")),
+    ("xor05", "xor", 2, 1, 0.001, include_str!("trained_networks/xor05.toml")),
+    ("adder05", "adder", 3, 3, 0.015, include_str!("trained_networks/adder05.toml")),
+    ("adder06", "adder", 3, 3, 0.007, include_str!("trained_networks/adder06.toml")),
+    ("adder07", "adder", 3, 3, 0.015, include_str!("trained_networks/adder07.toml")),
+    ("adder08", "adder", 3, 3, 0.005, include_str!("trained_networks/adder08.toml")),
+    ("adder09", "adder", 3, 3, 0.01, include_str!("trained_networks/adder09.toml")),
+    ("iris03", "iris", 4, 3, 10.599, include_str!("trained_networks/iris03.toml")),
+    ("iris04", "iris", 4, 3, 12.555, include_str!("trained_networks/iris04.toml")),
+    ("iris05", "iris", 4, 3, 4.779, include_str!("trained_networks/iris05.toml")),
+    ("iris06", "iris", 4, 3, 6.583, include_str!("trained_networks/iris06.toml")),
+    ("iris07", "iris", 4, 3, 4.101, include_str!("trained_networks/iris07.toml")),
+    ("iris08", "iris", 4, 3, 2.426, include_str!("trained_networks/iris08.toml")),
+];
+
+impl TrainedNetwork {
+    pub fn num_of_nodes(&self) -> usize {
+        self.property.nodes.len()
+    }
+
+    pub fn compatibility(&self, configuration: &DriverConfiguration) -> Compatibility {
+        if self.num_of_nodes() > configuration.max_network_size {
+            Compatibility::Incompatible
+        } else if self.info.num_of_input_nodes == configuration.num_of_input_nodes &&
+                  self.info.num_of_output_nodes == configuration.num_of_output_nodes {
+            Compatibility::Exact
+        } else {
+            Compatibility::Adaptable
+        }
+    }
+
+    fn shape_distance(&self, configuration: &DriverConfiguration) -> usize {
+        let input_distance = (self.info.num_of_input_nodes as isize - configuration.num_of_input_nodes as isize).abs();
+        let output_distance = (self.info.num_of_output_nodes as isize - configuration.num_of_output_nodes as isize).abs();
+
+        (input_distance + output_distance) as usize
+    }
+
+    pub fn shape_mapping(&self, configuration: &DriverConfiguration) -> ShapeMapping {
+        ShapeMapping::identity(self.info.num_of_input_nodes, self.info.num_of_output_nodes,
+            configuration.num_of_input_nodes, configuration.num_of_output_nodes)
+    }
+
+    pub fn to_network(&self, configuration: DriverConfiguration) -> Result<Network, Error> {
+        let property = self.property.adapt(&self.shape_mapping(&configuration))?;
+        let mut network = Network::new_with_property(configuration, property, &lineage::generate_id(&format!("{}-", self.info.name)));
+        network.lineage_mut().origin = Origin::Trained;
+        network.lineage_mut().source = Some(self.info.name.clone());
+        network.freeze_loaded_nodes(self.num_of_nodes());
+
+        let violations = network.validate();
+        if !violations.is_empty() {
+            return Err(InvalidNetwork { id: self.info.name.clone(), violations }.into())
+        }
+
+        Ok(network)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NetworkRegistry {
+    networks: Vec<TrainedNetwork>,
+}
+
+impl NetworkRegistry {
+    pub fn new() -> NetworkRegistry {
+        Default::default()
+    }
+
+    // All networks from src/trained_networks/ are embedded into the library
+    pub fn bundled() -> NetworkRegistry {
+        let mut registry = NetworkRegistry::new();
+
+        for &(name, task, num_of_input_nodes, num_of_output_nodes, error, data) in BUNDLED_NETWORKS.iter() {
+            let info = TrainedNetworkInfo {
+                name: name.to_string(),
+                task: task.to_string(),
+                num_of_input_nodes,
+                num_of_output_nodes,
+                error: Some(error),
+            };
+
+            registry.register(info, toml::from_str(data).unwrap());
+        }
+
+        registry
+    }
+
+    pub fn register(&mut self, info: TrainedNetworkInfo, property: Property) {
+        self.networks.push(TrainedNetwork {
+            info,
+            property,
+        });
+    }
+
+    // The directory must contain a "registry.toml" file which lists all the networks:
+    //
+    // [[networks]]
+    // file = "xor02.toml"
+    // name = "xor02"
+    // task = "xor"
+    // num_of_input_nodes = 2
+    // num_of_output_nodes = 1
+    // error = 0.001
+    pub fn register_directory<P: AsRef<Path>>(&mut self, directory: P) -> Result<usize, Error> {
+        let directory = directory.as_ref();
+        let manifest_file = directory.join("registry.toml");
+        info!("Register trained networks from: {}", manifest_file.display());
+
+        let manifest: RegistryManifest = toml::from_str(&fs::read_to_string(&manifest_file)?)?;
+        let num_of_networks = manifest.networks.len();
+
+        for entry in manifest.networks {
+            let data = fs::read_to_string(directory.join(&entry.file))?;
+            let property: Property = toml::from_str(&data)?;
+
+            if property.output_indices.len() != entry.info.num_of_output_nodes {
+                bail!("Trained network {}: num_of_output_nodes is {}, but the network has {} output indices",
+                    entry.info.name, entry.info.num_of_output_nodes, property.output_indices.len());
+            }
+
+            self.register(entry.info, property);
+        }
+
+        Ok(num_of_networks)
+    }
+
+    pub fn networks(&self) -> &[TrainedNetwork] {
+        &self.networks
+    }
+
+    pub fn find(&self, name: &str) -> Option<&TrainedNetwork> {
+        self.networks.iter().find(|network| network.info.name == name)
+    }
+
+    // Returns the networks that can be used as seeds, best fitting first:
+    // exact shape matches, then the ones with the smallest shape difference.
+    // Within the same group networks with lower error and fewer nodes come first.
+    pub fn select(&self, configuration: &DriverConfiguration, max_num_of_networks: usize) -> Vec<&TrainedNetwork> {
+        let mut candidates: Vec<&TrainedNetwork> = self.networks.iter().filter(
+            |network| network.compatibility(configuration) != Compatibility::Incompatible).collect();
+
+        candidates.sort_by(|n1, n2| {
+            let error1 = n1.info.error.unwrap_or(f64::MAX);
+            let error2 = n2.info.error.unwrap_or(f64::MAX);
+
+            n1.compatibility(configuration).cmp(&n2.compatibility(configuration))
+                .then(n1.shape_distance(configuration).cmp(&n2.shape_distance(configuration)))
+                .then(error1.partial_cmp(&error2).unwrap_or(Ordering::Equal))
+                .then(n1.num_of_nodes().cmp(&n2.num_of_nodes()))
+        });
+
+        candidates.truncate(max_num_of_networks);
+        candidates
+    }
+
+    pub fn seed_networks(&self, configuration: &DriverConfiguration) -> Vec<Network> {
+        self.select(configuration, configuration.max_seed_networks).iter().filter_map(|trained_network| {
+            match trained_network.to_network(configuration.clone()) {
+                Ok(network) => {
+                    info!("Use trained network: {} ({:?})", trained_network.info.name, trained_network.compatibility(configuration));
+                    Some(network)
+                }
+                Err(e) => {
+                    warn!("Could not adapt trained network {}: {}", trained_network.info.name, e);
+                    None
+                }
+            }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use driver::{TrainingData};
+    use test_support::{TempPath};
+
+    fn configuration(num_of_input_nodes: usize, num_of_output_nodes: usize) -> DriverConfiguration {
+        let mut configuration: DriverConfiguration = toml::from_str("").unwrap();
+        configuration.num_of_input_nodes = num_of_input_nodes;
+        configuration.num_of_output_nodes = num_of_output_nodes;
+        configuration
+    }
+
+    #[test]
+    fn bundled_contains_all_trained_networks() {
+        let registry = NetworkRegistry::bundled();
+        assert_eq!(registry.networks().len(), 15);
+        assert_eq!(registry.find("iris05").unwrap().info.num_of_input_nodes, 4);
+    }
+
+    fn task_data(task: &str) -> TrainingData {
+        // The adder examples count up: the output is the input + 1 as a 3 bit number
+        let bits = |number: usize| vec![((number >> 2) & 1) as f64, ((number >> 1) & 1) as f64, (number & 1) as f64];
+
+        match task {
+            "xor" => TrainingData {
+                provided_input: vec![vec![0.0, 0.0], vec![0.0, 1.0], vec![1.0, 0.0], vec![1.0, 1.0]],
+                expected_output: vec![vec![0.0], vec![1.0], vec![1.0], vec![0.0]],
+            },
+            "adder" => TrainingData {
+                provided_input: (0..8).map(bits).collect(),
+                expected_output: (0..8).map(|number| bits((number + 1) % 8)).collect(),
+            },
+            _ => toml::from_str(include_str!("../examples/iris1/iris_data.toml")).unwrap(),
+        }
+    }
+
+    #[test]
+    fn bundled_networks_solve_their_task() {
+        let registry = NetworkRegistry::bundled();
+
+        for trained_network in registry.networks() {
+            let info = &trained_network.info;
+            let training_data = task_data(&info.task);
+            assert_eq!(training_data.provided_input[0].len(), info.num_of_input_nodes);
+            assert_eq!(training_data.expected_output[0].len(), info.num_of_output_nodes);
+
+            // Error of a network that always returns 0, for these tasks no constant output does better
+            let baseline: f64 = training_data.expected_output.iter().flatten().map(|value| value.abs()).sum();
+
+            let mut network = trained_network.to_network(configuration(info.num_of_input_nodes, info.num_of_output_nodes)).unwrap();
+            let error = network.calculate_total_error(&training_data);
+            assert!(error <= info.error.unwrap(), "{}: error {} > {}", info.name, error, info.error.unwrap());
+            assert!(error < 0.1 * baseline, "{}: error {}, baseline {}", info.name, error, baseline);
+        }
+    }
+
+    #[test]
+    fn select_prefers_exact_shape() {
+        let registry = NetworkRegistry::bundled();
+        let selected = registry.select(&configuration(2, 1), 3);
+
+        assert_eq!(selected.len(), 3);
+        for network in selected {
+            assert_eq!(network.info.task, "xor");
+        }
+    }
+
+    #[test]
+    fn register_directory_reads_manifest() {
+        let directory = TempPath::new("registry_test");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("xor02.toml"), include_str!("trained_networks/xor02.toml")).unwrap();
+        fs::write(directory.join("registry.toml"), "[[networks]]\nfile = \"xor02.toml\"\nname = \"my_xor\"\ntask = \"xor\"\nnum_of_input_nodes = 2\nnum_of_output_nodes = 1\nerror = 0.5\n").unwrap();
+
+        let mut registry = NetworkRegistry::new();
+        assert_eq!(registry.register_directory(&directory).unwrap(), 1);
+        assert_eq!(registry.find("my_xor").unwrap().info.error, Some(0.5));
+    }
+
+    #[test]
+    fn adaptable_networks_get_the_configured_shape() {
+        let registry = NetworkRegistry::bundled();
+        let configuration = configuration(3, 3);
+        let network = registry.find("xor02").unwrap().to_network(configuration).unwrap();
+
+        assert_eq!(network.get_property().output_indices.len(), 3);
+        assert_eq!(network.num_of_nodes(), 4);
+    }
+
+    #[test]
+    fn select_skips_networks_that_are_too_big() {
+        let registry = NetworkRegistry::bundled();
+        let mut configuration = configuration(3, 3);
+        configuration.max_network_size = 4;
+
+        for network in registry.select(&configuration, 100) {
+            assert!(network.num_of_nodes() <= 4);
+        }
+    }
+}
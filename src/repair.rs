@@ -0,0 +1,62 @@
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadMode {
+    // Reject networks that do not fit the configuration
+    Strict,
+    // Change the network until it fits and report every change
+    Repair,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepairChange {
+    AddedNode,
+    RemovedNodes { num_of_nodes: usize, max_network_size: usize },
+    ResizedOutputs { old_len: usize, new_len: usize },
+    OutputRedirected { output: usize, old_index: usize, new_index: usize },
+    ConnectionRemoved { node: usize, index: usize, weight: f64 },
+    ConnectionAdded { node: usize, index: usize, weight: f64 },
+    BiasReset { node: usize, old_bias: f64 },
+    WeightReset { node: usize, index: usize, old_weight: f64 },
+}
+
+impl fmt::Display for RepairChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::RepairChange::*;
+        match *self {
+            AddedNode =>
+                write!(f, "network had no nodes, added a random node"),
+            RemovedNodes { num_of_nodes, max_network_size } =>
+                write!(f, "removed {} nodes beyond max_network_size {}", num_of_nodes, max_network_size),
+            ResizedOutputs { old_len, new_len } =>
+                write!(f, "changed number of outputs from {} to {}", old_len, new_len),
+            OutputRedirected { output, old_index, new_index } =>
+                write!(f, "output {} reads value {} instead of {}", output, new_index, old_index),
+            ConnectionRemoved { node, index, weight } =>
+                write!(f, "removed connection from value {} (weight {}) in node {}", index, weight, node),
+            ConnectionAdded { node, index, weight } =>
+                write!(f, "added connection from value {} (weight {}) to node {}", index, weight, node),
+            BiasReset { node, old_bias } =>
+                write!(f, "reset bias {} of node {} to 0.0", old_bias, node),
+            WeightReset { node, index, old_weight } =>
+                write!(f, "reset weight {} for value {} of node {} to 0.0", old_weight, index, node),
+        }
+    }
+}
+
+// Everything that was changed to make a network fit the configuration
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RepairReport {
+    pub changes: Vec<RepairChange>,
+}
+
+impl RepairReport {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn push(&mut self, change: RepairChange) {
+        self.changes.push(change);
+    }
+}
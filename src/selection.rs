@@ -0,0 +1,292 @@
+use std::fmt;
+
+use rand::{Rng, RngCore};
+
+// Which built in strategy the driver uses, see DriverConfiguration::selection
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionMethod {
+    Truncation,
+    Tournament,
+    FitnessProportional,
+    Rank,
+    // (mu + lambda): parents and offspring compete
+    Plus,
+    // (mu, lambda): only offspring survive, except the elite
+    Comma,
+}
+
+// One network of the current population
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Candidate {
+    pub error: f64,
+    // Created by the previous selection
+    pub is_offspring: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SelectionSettings {
+    // mu: size of the population without offspring
+    pub num_of_networks: usize,
+    // The best networks always survive
+    pub elitism: usize,
+    // lambda: number of copies made each batch iteration
+    pub num_of_offspring: usize,
+    pub clone_threshold: f64,
+}
+
+// The candidates are sorted, best first.
+// Returns the indices of the networks for the next population. An index that appears more than once
+// is copied, the copy becomes a new network (offspring) that is trained on its own.
+pub trait SelectionStrategy: fmt::Debug + Send + Sync {
+    fn select(&self, candidates: &[Candidate], settings: &SelectionSettings, rng: &mut dyn RngCore) -> Vec<usize>;
+}
+
+// Survivors (each index once, sorted) and the indices that must be copied
+pub fn split_selection(selected: &[usize], num_of_candidates: usize) -> (Vec<usize>, Vec<usize>) {
+    let mut used = vec![false; num_of_candidates];
+    let mut survivors = Vec::new();
+    let mut copies = Vec::new();
+
+    for index in selected {
+        assert!(*index < num_of_candidates, "Selection returned invalid index: {}, number of networks: {}", index, num_of_candidates);
+
+        if used[*index] {
+            copies.push(*index);
+        } else {
+            used[*index] = true;
+            survivors.push(*index);
+        }
+    }
+
+    survivors.sort_unstable();
+
+    (survivors, copies)
+}
+
+// Elites first, then the remaining survivors and the parents of the offspring from pick()
+fn select_with<F: FnMut(&mut dyn RngCore) -> usize>(num_of_candidates: usize, settings: &SelectionSettings, rng: &mut dyn RngCore, mut pick: F) -> Vec<usize> {
+    let num_of_survivors = settings.num_of_networks.min(num_of_candidates);
+    let num_of_elites = settings.elitism.min(num_of_survivors);
+    let mut result: Vec<usize> = (0..num_of_elites).collect();
+
+    for _ in num_of_elites..(num_of_survivors + settings.num_of_offspring) {
+        result.push(pick(rng));
+    }
+
+    result
+}
+
+// Index chosen with a probability proportional to its weight
+fn roulette(weights: &[f64], rng: &mut dyn RngCore) -> usize {
+    let total: f64 = weights.iter().sum();
+
+    if total.is_finite() && total > 0.0 {
+        let mut value = rng.gen_range(0.0, total);
+
+        for (index, weight) in weights.iter().enumerate() {
+            if value < *weight {
+                return index
+            }
+            value -= weight;
+        }
+    }
+
+    rng.gen_range(0, weights.len())
+}
+
+// Keep the best networks and clone the best one if it is clearly better than the second
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Truncation;
+
+impl SelectionStrategy for Truncation {
+    fn select(&self, candidates: &[Candidate], settings: &SelectionSettings, _rng: &mut dyn RngCore) -> Vec<usize> {
+        let num_of_survivors = settings.num_of_networks.min(candidates.len());
+        let mut result: Vec<usize> = (0..num_of_survivors).collect();
+
+        if settings.num_of_offspring == 0 {
+            // Try to avoid cloning local optimum over and over again
+            if num_of_survivors > 1 && candidates[0].error <= settings.clone_threshold * candidates[1].error {
+                result.push(0);
+            }
+        } else {
+            result.extend((0..settings.num_of_offspring).map(|index| index % num_of_survivors));
+        }
+
+        result
+    }
+}
+
+// The best of `size` randomly chosen networks wins
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tournament {
+    pub size: usize,
+}
+
+impl SelectionStrategy for Tournament {
+    fn select(&self, candidates: &[Candidate], settings: &SelectionSettings, rng: &mut dyn RngCore) -> Vec<usize> {
+        let len = candidates.len();
+
+        select_with(len, settings, rng, |rng| {
+            // Candidates are sorted, so the smallest index is the best one
+            (0..self.size.max(1)).map(|_| rng.gen_range(0, len)).min().unwrap_or(0)
+        })
+    }
+}
+
+// Probability proportional to 1 / (1 + error - lowest error), the best network has weight 1.
+// Networks with NaN or inf errors are not chosen.
+fn proportional_weights(candidates: &[Candidate]) -> Vec<f64> {
+    let min_error = candidates.iter().map(|candidate| candidate.error).filter(|error| error.is_finite()).fold(f64::INFINITY, f64::min);
+
+    candidates.iter().map(|candidate| {
+        if candidate.error.is_finite() {
+            1.0 / (1.0 + candidate.error - min_error)
+        } else {
+            0.0
+        }
+    }).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FitnessProportional;
+
+impl SelectionStrategy for FitnessProportional {
+    fn select(&self, candidates: &[Candidate], settings: &SelectionSettings, rng: &mut dyn RngCore) -> Vec<usize> {
+        let weights = proportional_weights(candidates);
+
+        select_with(candidates.len(), settings, rng, |rng| roulette(&weights, rng))
+    }
+}
+
+// Linear ranking: the best network has weight n, the worst has weight 1
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Rank;
+
+impl SelectionStrategy for Rank {
+    fn select(&self, candidates: &[Candidate], settings: &SelectionSettings, rng: &mut dyn RngCore) -> Vec<usize> {
+        let len = candidates.len();
+        let weights: Vec<f64> = (0..len).map(|index| (len - index) as f64).collect();
+
+        select_with(len, settings, rng, |rng| roulette(&weights, rng))
+    }
+}
+
+// (mu + lambda): the best mu networks of parents and offspring survive,
+// lambda offspring are copies of random survivors
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Plus;
+
+impl SelectionStrategy for Plus {
+    fn select(&self, candidates: &[Candidate], settings: &SelectionSettings, rng: &mut dyn RngCore) -> Vec<usize> {
+        let num_of_survivors = settings.num_of_networks.min(candidates.len());
+        let mut result: Vec<usize> = (0..num_of_survivors).collect();
+
+        for _ in 0..settings.num_of_offspring {
+            result.push(rng.gen_range(0, num_of_survivors));
+        }
+
+        result
+    }
+}
+
+// (mu, lambda): besides the elite only offspring survive, lambda new offspring are copies of random survivors.
+// If there are not enough offspring (for example in the first batch iteration) the best parents fill the gap.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Comma;
+
+impl SelectionStrategy for Comma {
+    fn select(&self, candidates: &[Candidate], settings: &SelectionSettings, rng: &mut dyn RngCore) -> Vec<usize> {
+        let num_of_survivors = settings.num_of_networks.min(candidates.len());
+        let num_of_elites = settings.elitism.min(num_of_survivors);
+        let mut result: Vec<usize> = (0..num_of_elites).collect();
+
+        let offspring = (num_of_elites..candidates.len()).filter(|index| candidates[*index].is_offspring);
+        let parents = (num_of_elites..candidates.len()).filter(|index| !candidates[*index].is_offspring);
+        result.extend(offspring.chain(parents).take(num_of_survivors - num_of_elites));
+
+        let survivors = result.clone();
+        for _ in 0..settings.num_of_offspring {
+            result.push(*rng.choose(&survivors).unwrap());
+        }
+
+        result
+    }
+}
+
+pub fn new_strategy(method: SelectionMethod, tournament_size: usize) -> Box<dyn SelectionStrategy> {
+    match method {
+        SelectionMethod::Truncation => Box::new(Truncation),
+        SelectionMethod::Tournament => Box::new(Tournament { size: tournament_size }),
+        SelectionMethod::FitnessProportional => Box::new(FitnessProportional),
+        SelectionMethod::Rank => Box::new(Rank),
+        SelectionMethod::Plus => Box::new(Plus),
+        SelectionMethod::Comma => Box::new(Comma),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rand::{XorShiftRng, SeedableRng};
+
+    fn candidates() -> Vec<Candidate> {
+        [0.1, 0.2, 0.3, 0.4, 0.5, 0.6].iter().enumerate().map(|(index, error)| Candidate {
+            error: *error,
+            is_offspring: index >= 4,
+        }).collect()
+    }
+
+    fn settings(num_of_offspring: usize) -> SelectionSettings {
+        SelectionSettings {
+            num_of_networks: 4,
+            elitism: 1,
+            num_of_offspring,
+            clone_threshold: 0.9,
+        }
+    }
+
+    #[test]
+    fn truncation_clones_clearly_better_network() {
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+
+        assert_eq!(Truncation.select(&candidates(), &settings(0), &mut rng), vec![0, 1, 2, 3, 0]);
+        assert_eq!(Truncation.select(&candidates(), &settings(2), &mut rng), vec![0, 1, 2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn strategies_keep_elite_and_size() {
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+        let methods = [SelectionMethod::Tournament, SelectionMethod::FitnessProportional, SelectionMethod::Rank,
+            SelectionMethod::Plus, SelectionMethod::Comma];
+
+        for method in &methods {
+            let selected = new_strategy(*method, 3).select(&candidates(), &settings(2), &mut rng);
+
+            assert_eq!(selected.len(), 6);
+            assert_eq!(selected[0], 0);
+            assert!(selected.iter().all(|index| *index < 6));
+        }
+    }
+
+    #[test]
+    fn comma_prefers_offspring() {
+        let mut rng = XorShiftRng::from_seed([1; 16]);
+        let (survivors, copies) = split_selection(&Comma.select(&candidates(), &settings(0), &mut rng), 6);
+
+        assert_eq!(survivors, vec![0, 1, 4, 5]);
+        assert!(copies.is_empty());
+    }
+
+    #[test]
+    fn proportional_weights_are_relative_to_the_best() {
+        let errors = |errors: &[f64]| -> Vec<Candidate> {
+            errors.iter().map(|error| Candidate { error: *error, is_offspring: false }).collect()
+        };
+
+        assert_eq!(proportional_weights(&errors(&[10.0, 11.0, 13.0, f64::NAN])), vec![1.0, 0.5, 0.25, 0.0]);
+        // Equal errors get the same weight, no matter how small they are
+        assert_eq!(proportional_weights(&errors(&[0.0, 0.0, 1.0, f64::INFINITY])), vec![1.0, 1.0, 0.5, 0.0]);
+    }
+}
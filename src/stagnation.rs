@@ -0,0 +1,133 @@
+// Detects a population that does not make progress anymore, see DriverConfiguration::stagnation_iterations
+
+use std::f64;
+use std::fmt;
+
+use driver::{DriverConfiguration};
+use mutation::{ConfigurationError};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StagnationRule {
+    // The best error did not improve for stagnation_iterations batch iterations
+    NoImprovement,
+    // The same network was in first place for max_first_place_iterations batch iterations
+    FirstPlace,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StagnationAction {
+    // Replace the worse half of the population with new random networks
+    ReRandomizeBottomHalf,
+    // Add num_of_injected_networks new random networks
+    InjectNewNetworks,
+    // Multiply the mutation step sizes of all networks except the best one by mutation_boost
+    BoostMutation,
+}
+
+impl fmt::Display for StagnationRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StagnationRule::NoImprovement => write!(f, "no_improvement"),
+            StagnationRule::FirstPlace => write!(f, "first_place"),
+        }
+    }
+}
+
+impl fmt::Display for StagnationAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StagnationAction::ReRandomizeBottomHalf => write!(f, "re_randomize_bottom_half"),
+            StagnationAction::InjectNewNetworks => write!(f, "inject_new_networks"),
+            StagnationAction::BoostMutation => write!(f, "boost_mutation"),
+        }
+    }
+}
+
+pub fn validate(configuration: &DriverConfiguration) -> Result<(), ConfigurationError> {
+    if configuration.mutation_boost.is_finite() && configuration.mutation_boost >= 1.0 {
+        Ok(())
+    } else {
+        Err(ConfigurationError { errors: vec![format!("mutation_boost must be finite and >= 1, given: {}", configuration.mutation_boost)] })
+    }
+}
+
+// Kept by the driver over all calls of train()
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagnationMonitor {
+    best_error: f64,
+    num_of_iterations_without_improvement: usize,
+    // Id and first_place_counter of the best network when the first place rule fired,
+    // only the first places after that are counted for this network
+    first_place_start: Option<(String, u64)>,
+}
+
+impl Default for StagnationMonitor {
+    fn default() -> StagnationMonitor {
+        StagnationMonitor {
+            best_error: f64::MAX,
+            num_of_iterations_without_improvement: 0,
+            first_place_start: None,
+        }
+    }
+}
+
+impl StagnationMonitor {
+    // Called once per batch iteration with the best error, the id and the first place counter of the best network.
+    // After a rule has fired its counting starts again, the first place counter of the network is not changed.
+    pub fn update(&mut self, best_error: f64, best_id: &str, first_place_counter: u64, configuration: &DriverConfiguration) -> Option<StagnationRule> {
+        let num_of_first_places = match self.first_place_start {
+            Some((ref id, start)) if id == best_id => first_place_counter.saturating_sub(start),
+            _ => first_place_counter,
+        };
+
+        if best_error < self.best_error {
+            self.best_error = best_error;
+            self.num_of_iterations_without_improvement = 0;
+        } else {
+            self.num_of_iterations_without_improvement += 1;
+        }
+
+        if configuration.stagnation_iterations > 0 && self.num_of_iterations_without_improvement >= configuration.stagnation_iterations {
+            self.num_of_iterations_without_improvement = 0;
+            Some(StagnationRule::NoImprovement)
+        } else if configuration.max_first_place_iterations > 0 && num_of_first_places >= configuration.max_first_place_iterations {
+            self.first_place_start = Some((best_id.to_string(), first_place_counter));
+            Some(StagnationRule::FirstPlace)
+        } else {
+            None
+        }
+    }
+
+    pub fn num_of_iterations_without_improvement(&self) -> usize {
+        self.num_of_iterations_without_improvement
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use toml;
+
+    #[test]
+    fn rules_fire_and_restart() {
+        let mut configuration: DriverConfiguration = toml::from_str("").unwrap();
+        configuration.stagnation_iterations = 2;
+        configuration.max_first_place_iterations = 5;
+        let mut monitor = StagnationMonitor::default();
+
+        assert_eq!(monitor.update(1.0, "n1", 1, &configuration), None);
+        assert_eq!(monitor.update(1.0, "n1", 2, &configuration), None);
+        assert_eq!(monitor.update(1.0, "n1", 3, &configuration), Some(StagnationRule::NoImprovement));
+        assert_eq!(monitor.update(0.5, "n1", 4, &configuration), None);
+        assert_eq!(monitor.update(0.4, "n1", 5, &configuration), Some(StagnationRule::FirstPlace));
+        assert_eq!(monitor.num_of_iterations_without_improvement(), 0);
+
+        // The counting of first places starts again for the same network, other networks keep their counters
+        assert_eq!(monitor.update(0.3, "n1", 9, &configuration), None);
+        assert_eq!(monitor.update(0.2, "n1", 10, &configuration), Some(StagnationRule::FirstPlace));
+        assert_eq!(monitor.update(0.1, "n2", 5, &configuration), Some(StagnationRule::FirstPlace));
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+// Why Driver::train() returned
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopReason {
+    // All batch iterations done
+    Completed,
+    TimeBudget,
+    EvaluationBudget,
+    Cancelled,
+    // The best network reached the desired error on the full training set or the validation set
+    DesiredErrorReached,
+}
+
+// Can be cloned and sent to another thread, training stops at the next safe point after cancel()
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    // Needed before the next call to train() after a cancellation
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
+
+// Shared by all networks during one call of Driver::train()
+#[derive(Debug)]
+pub struct TrainingBudget {
+    start_time: Instant,
+    max_duration: Option<Duration>,
+    max_evaluations: Option<usize>,
+    evaluations: AtomicUsize,
+    token: CancellationToken,
+}
+
+impl TrainingBudget {
+    pub fn new(max_seconds: Option<f64>, max_evaluations: Option<usize>, token: CancellationToken) -> TrainingBudget {
+        TrainingBudget {
+            start_time: Instant::now(),
+            max_duration: max_seconds.map(|seconds| Duration::from_millis((seconds.max(0.0) * 1000.0) as u64)),
+            max_evaluations,
+            evaluations: AtomicUsize::new(0),
+            token,
+        }
+    }
+
+    // One evaluation is one calculation of the error for a batch
+    pub fn add_evaluations(&self, num_of_evaluations: usize) {
+        self.evaluations.fetch_add(num_of_evaluations, Ordering::SeqCst);
+    }
+
+    pub fn evaluations(&self) -> usize {
+        self.evaluations.load(Ordering::SeqCst)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start_time.elapsed()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    // Time left of the time budget, used for the jobs of worker processes
+    pub fn remaining_seconds(&self) -> Option<f64> {
+        self.max_duration.map(|max_duration| {
+            let remaining = max_duration.checked_sub(self.elapsed()).unwrap_or_default();
+            (remaining.as_secs() as f64) + ((remaining.subsec_nanos() as f64) * 1e-9)
+        })
+    }
+
+    pub fn remaining_evaluations(&self) -> Option<usize> {
+        self.max_evaluations.map(|max_evaluations| max_evaluations.saturating_sub(self.evaluations()))
+    }
+
+    // None: training can go on
+    pub fn check(&self) -> Option<StopReason> {
+        if self.token.is_cancelled() {
+            return Some(StopReason::Cancelled)
+        }
+
+        if self.max_evaluations.map_or(false, |max_evaluations| self.evaluations() >= max_evaluations) {
+            return Some(StopReason::EvaluationBudget)
+        }
+
+        if self.max_duration.map_or(false, |max_duration| self.elapsed() >= max_duration) {
+            return Some(StopReason::TimeBudget)
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::thread;
+
+    #[test]
+    fn cancel_from_other_thread() {
+        let token = CancellationToken::new();
+        let budget = TrainingBudget::new(None, None, token.clone());
+        assert_eq!(budget.check(), None);
+
+        thread::spawn(move || token.cancel()).join().unwrap();
+
+        assert_eq!(budget.check(), Some(StopReason::Cancelled));
+    }
+
+    #[test]
+    fn evaluation_budget() {
+        let budget = TrainingBudget::new(Some(1000.0), Some(10), CancellationToken::new());
+        budget.add_evaluations(9);
+        assert_eq!(budget.check(), None);
+
+        budget.add_evaluations(1);
+        assert_eq!(budget.check(), Some(StopReason::EvaluationBudget));
+        assert_eq!(budget.remaining_evaluations(), Some(0));
+    }
+
+    #[test]
+    fn remaining_budget() {
+        let budget = TrainingBudget::new(Some(1000.0), Some(10), CancellationToken::new());
+        budget.add_evaluations(4);
+
+        assert_eq!(budget.remaining_evaluations(), Some(6));
+        let remaining_seconds = budget.remaining_seconds().unwrap();
+        assert!(remaining_seconds > 990.0 && remaining_seconds <= 1000.0);
+
+        let unlimited = TrainingBudget::new(None, None, CancellationToken::new());
+        assert_eq!(unlimited.remaining_seconds(), None);
+        assert_eq!(unlimited.remaining_evaluations(), None);
+    }
+}
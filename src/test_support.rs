@@ -0,0 +1,34 @@
+// Helpers shared by the tests of several modules
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// File or directory in the temp directory for tests. The process id keeps parallel test runs apart,
+// the path is removed again when the TempPath is dropped.
+pub(crate) struct TempPath(PathBuf);
+
+impl TempPath {
+    pub fn new(name: &str) -> TempPath {
+        TempPath(::std::env::temp_dir().join(format!("gronn_{}_{}", ::std::process::id(), name)))
+    }
+}
+
+impl ::std::ops::Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = if self.0.is_dir() { fs::remove_dir_all(&self.0) } else { fs::remove_file(&self.0) };
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+
+use failure::Fail;
+
+use driver::{DriverConfiguration};
+use property::{Property};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyViolation {
+    NoNodes,
+    TooManyNodes { num_of_nodes: usize, max_network_size: usize },
+    WrongNumberOfOutputs { expected: usize, found: usize },
+    OutputIndexOutOfRange { output: usize, index: usize, num_of_values: usize },
+    NodeWithoutConnections { node: usize },
+    ConnectionIndexOutOfRange { node: usize, index: usize, num_of_values: usize },
+    NonFiniteBias { node: usize, bias: f64 },
+    NonFiniteWeight { node: usize, index: usize, weight: f64 },
+}
+
+impl fmt::Display for PropertyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::PropertyViolation::*;
+        match *self {
+            NoNodes => write!(f, "network has no nodes"),
+            TooManyNodes { num_of_nodes, max_network_size } =>
+                write!(f, "network has {} nodes, max_network_size is {}", num_of_nodes, max_network_size),
+            WrongNumberOfOutputs { expected, found } =>
+                write!(f, "network has {} output indices, expected {}", found, expected),
+            OutputIndexOutOfRange { output, index, num_of_values } =>
+                write!(f, "output {} reads value {}, but there are only {} values", output, index, num_of_values),
+            NodeWithoutConnections { node } =>
+                write!(f, "node {} has no connections", node),
+            ConnectionIndexOutOfRange { node, index, num_of_values } =>
+                write!(f, "node {} reads value {}, but there are only {} values", node, index, num_of_values),
+            NonFiniteBias { node, bias } =>
+                write!(f, "node {} has bias {}", node, bias),
+            NonFiniteWeight { node, index, weight } =>
+                write!(f, "node {} has weight {} for value {}", node, weight, index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidNetwork {
+    pub id: String,
+    pub violations: Vec<PropertyViolation>,
+}
+
+impl fmt::Display for InvalidNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let violations: Vec<String> = self.violations.iter().map(|violation| violation.to_string()).collect();
+        write!(f, "Invalid network {}: {}", self.id, violations.join("; "))
+    }
+}
+
+impl Fail for InvalidNetwork {}
+
+impl Property {
+    // Checks that the network can be calculated with the given configuration
+    pub fn validate(&self, configuration: &DriverConfiguration) -> Vec<PropertyViolation> {
+        let mut violations = Vec::new();
+        let num_of_nodes = self.nodes.len();
+        let num_of_values = configuration.num_of_input_nodes + num_of_nodes;
+
+        if num_of_nodes == 0 {
+            violations.push(PropertyViolation::NoNodes);
+        }
+
+        if num_of_nodes > configuration.max_network_size {
+            violations.push(PropertyViolation::TooManyNodes { num_of_nodes, max_network_size: configuration.max_network_size });
+        }
+
+        if self.output_indices.len() != configuration.num_of_output_nodes {
+            violations.push(PropertyViolation::WrongNumberOfOutputs { expected: configuration.num_of_output_nodes, found: self.output_indices.len() });
+        }
+
+        for (output, index) in self.output_indices.iter().enumerate() {
+            if *index >= num_of_values {
+                violations.push(PropertyViolation::OutputIndexOutOfRange { output, index: *index, num_of_values });
+            }
+        }
+
+        for (node_index, node) in self.nodes.iter().enumerate() {
+            if node.num_of_connections() == 0 {
+                violations.push(PropertyViolation::NodeWithoutConnections { node: node_index });
+            }
+
+            if !node.bias().is_finite() {
+                violations.push(PropertyViolation::NonFiniteBias { node: node_index, bias: node.bias() });
+            }
+
+            for position in 0..node.num_of_connections() {
+                let (index, weight) = node.connection(position);
+
+                if index >= num_of_values {
+                    violations.push(PropertyViolation::ConnectionIndexOutOfRange { node: node_index, index, num_of_values });
+                }
+
+                if !weight.is_finite() {
+                    violations.push(PropertyViolation::NonFiniteWeight { node: node_index, index, weight });
+                }
+            }
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use toml;
+
+    fn configuration() -> DriverConfiguration {
+        toml::from_str("
+            num_of_input_nodes = 2
+            num_of_output_nodes = 1
+        ").unwrap()
+    }
+
+    fn xor02() -> Property {
+        toml::from_str(include_str!("trained_networks/xor02.toml")).unwrap()
+    }
+
+    #[test]
+    fn trained_network_is_valid() {
+        assert!(xor02().validate(&configuration()).is_empty());
+    }
+
+    #[test]
+    fn violations_are_reported() {
+        let mut property = xor02();
+        property.output_indices = vec![4, 0];
+        property.nodes[1].set_connection(0, 7, f64::NAN);
+
+        let violations = property.validate(&configuration());
+
+        assert_eq!(violations.len(), 4);
+        assert_eq!(violations[..3], [
+            PropertyViolation::WrongNumberOfOutputs { expected: 1, found: 2 },
+            PropertyViolation::OutputIndexOutOfRange { output: 0, index: 4, num_of_values: 4 },
+            PropertyViolation::ConnectionIndexOutOfRange { node: 1, index: 7, num_of_values: 4 },
+        ]);
+
+        // NaN != NaN, so check the weight separately
+        match violations[3] {
+            PropertyViolation::NonFiniteWeight { node: 1, index: 7, weight } => assert!(weight.is_nan()),
+            ref violation => panic!("Unexpected violation: {}", violation),
+        }
+    }
+}